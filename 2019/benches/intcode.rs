@@ -0,0 +1,92 @@
+//! Interpreter vs. translated execution on the heaviest IntCode puzzles, and
+//! batches of short runs one at a time vs. across all CPUs.
+//! Run from the crate root with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use advent_of_code_2019::intcode::{IntCode, RunState};
+use advent_of_code_2019::intcode::batch::Batch;
+
+fn load(day: u32) -> Vec<isize> {
+    let path = format!("input/2019/day{}.txt", day);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    text.trim().split(',').map(|w| w.parse().unwrap()).collect()
+}
+
+fn machine(program: &[isize], inputs: &[isize], translate: bool) -> IntCode {
+    let mut comp = IntCode::with_inputs(program, inputs);
+    if translate {
+        comp.enable_translation();
+    }
+    comp
+}
+
+/// Plays breakout to the end by keeping the paddle under the ball.
+fn play(mut comp: IntCode) -> isize {
+    let (mut ball_x, mut paddle_x, mut score) : (isize, isize, isize) = (0, 0, 0);
+    loop {
+        match comp.resume().unwrap() {
+            RunState::Halted => return score,
+            RunState::NeedsInput => comp.push_input((ball_x - paddle_x).signum()),
+            RunState::Output(x) => {
+                let y = comp.expect_output().unwrap();
+                let tile = comp.expect_output().unwrap();
+                match (x, y, tile) {
+                    (-1, 0, _) => score = tile,
+                    (_, _, 3) => paddle_x = x,
+                    (_, _, 4) => ball_x = x,
+                    _ => {},
+                }
+            },
+        }
+    }
+}
+
+fn day9(c: &mut Criterion) {
+    let program = load(9);
+    assert_eq!(
+        machine(&program, &[2], false).run_to_halt().unwrap(),
+        machine(&program, &[2], true).run_to_halt().unwrap());
+
+    let mut group = c.benchmark_group("day9 boost");
+    group.sample_size(10);
+    for &(name, translate) in &[("interpreted", false), ("translated", true)] {
+        group.bench_function(name, |b| b.iter(|| {
+            machine(&program, &[2], translate).run_to_halt().unwrap()
+        }));
+    }
+    group.finish();
+}
+
+fn day13(c: &mut Criterion) {
+    let mut program = load(13);
+    program[0] = 2;
+    assert_eq!(play(machine(&program, &[], false)), play(machine(&program, &[], true)));
+
+    let mut group = c.benchmark_group("day13 breakout");
+    group.sample_size(10);
+    for &(name, translate) in &[("interpreted", false), ("translated", true)] {
+        group.bench_function(name, |b| b.iter(|| play(machine(&program, &[], translate))));
+    }
+    group.finish();
+}
+
+fn batch(c: &mut Criterion) {
+    // BOOST's self-test mode is a short run, like probing one point of a scan.
+    let program = load(9);
+    let mut batch = Batch::new(&program);
+    for _ in 0..200 {
+        batch.job(&[1]);
+    }
+    let sequential = batch.clone().workers(1);
+    assert_eq!(sequential.run(), batch.run());
+
+    let mut group = c.benchmark_group("batch of 200 day9 self-tests");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| b.iter(|| sequential.run().unwrap()));
+    group.bench_function("parallel", |b| b.iter(|| batch.run().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, day9, day13, batch);
+criterion_main!(benches);
@@ -0,0 +1,156 @@
+//! Interactive debugger for IntCode programs.
+//!
+//!     intcode-debug <day|path> [--set addr=value]... [--script file] [--trace file] [--record file] [--replay file] [--dot file]
+//!
+//! A bare day number loads `input/2019/day<N>.txt`. Commands are read from the
+//! script file first (if any) and then from stdin, so a session can also be
+//! piped in. With `--trace` every executed instruction is written to the
+//! file and a profile is printed on exit. `--record` saves every input and
+//! output of the session on exit, and `--replay` checks a saved session
+//! against the program and exits. `--dot` writes the program's control-flow
+//! graph in Graphviz format, summarizes it and exits.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use advent_of_code_2019::intcode::IntCode;
+use advent_of_code_2019::intcode::cfg::analyze;
+use advent_of_code_2019::intcode::debugger::Debugger;
+use advent_of_code_2019::intcode::session::Session;
+use advent_of_code_2019::intcode::trace::Tracer;
+
+fn usage() -> ! {
+    eprintln!("usage: intcode-debug <day|path> [--set addr=value]... [--script file] [--trace file] [--record file] [--replay file] [--dot file]");
+    std::process::exit(2);
+}
+
+fn load(arg: &str) -> Result<Vec<isize>, String> {
+    let path = match arg.parse::<u32>() {
+        Ok(day) => format!("input/2019/day{}.txt", day),
+        Err(_) => arg.to_string(),
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    text.trim().split(',')
+        .map(|w| w.parse().map_err(|_| format!("{}: not a number: '{}'", path, w)))
+        .collect()
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let program = args.next().unwrap_or_else(|| usage());
+    let mut program = load(&program).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let mut script = Vec::new();
+    let mut tracer = None;
+    let mut recording = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--set" => {
+                let patch = args.next().unwrap_or_else(|| usage());
+                let mut parts = patch.splitn(2, '=').map(|p| p.parse::<isize>());
+                match (parts.next(), parts.next()) {
+                    (Some(Ok(addr)), Some(Ok(value))) if (addr as usize) < program.len() => {
+                        program[addr as usize] = value;
+                    },
+                    _ => usage(),
+                }
+            },
+            "--script" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+                    eprintln!("{}: {}", path, e);
+                    std::process::exit(1);
+                });
+                script.extend(text.lines().map(str::to_string));
+            },
+            "--trace" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                let trace = Tracer::to_file(&path).unwrap_or_else(|e| {
+                    eprintln!("{}: {}", path, e);
+                    std::process::exit(1);
+                });
+                tracer = Some(trace.shared());
+            },
+            "--record" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                recording = Some((path, Session::new().shared()));
+            },
+            "--replay" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                let session = Session::load(&path).unwrap_or_else(|e| {
+                    eprintln!("{}: {}", path, e);
+                    std::process::exit(1);
+                });
+                match session.replay(IntCode::new(&program)) {
+                    Ok(()) => println!("replayed {} events", session.events.len()),
+                    Err(e) => {
+                        eprintln!("{}: {}", path, e);
+                        std::process::exit(1);
+                    },
+                }
+                return;
+            },
+            "--dot" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                let analysis = analyze(&program);
+                if let Err(e) = fs::write(&path, analysis.to_dot()) {
+                    eprintln!("{}: {}", path, e);
+                    std::process::exit(1);
+                }
+                println!("{} blocks, {} indirect jumps, {} inputs, {} outputs",
+                    analysis.blocks.len(), analysis.indirect_jumps.len(),
+                    analysis.inputs.len(), analysis.outputs.len());
+                println!("unreachable: {:?}", analysis.unreachable);
+                println!("self-modifying writes: {:?}", analysis.self_modifying);
+                println!("invalid jump targets: {:?}", analysis.invalid_targets);
+                return;
+            },
+            _ => usage(),
+        }
+    }
+
+    let mut comp = IntCode::new(&program);
+    if let Some(tracer) = &tracer {
+        comp.set_tracer(tracer.clone());
+    }
+    if let Some((_, session)) = &recording {
+        comp.set_recorder(session.clone());
+    }
+    let mut debugger = Debugger::new(comp);
+    println!("{}", debugger.execute("regs").unwrap());
+
+    let stdin = io::stdin();
+    let mut commands = script.into_iter().map(Ok).chain(stdin.lock().lines());
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let command = match commands.next() {
+            Some(Ok(command)) => command,
+            _ => break,
+        };
+        match command.trim() {
+            "q" | "quit" => break,
+            command => match debugger.execute(command) {
+                Ok(text) => if !text.is_empty() { println!("{}", text) },
+                Err(message) => println!("error: {}", message),
+            },
+        }
+    }
+
+    if let Some(tracer) = tracer {
+        let mut tracer = tracer.lock().unwrap();
+        if let Err(e) = tracer.flush() {
+            eprintln!("couldn't write the trace: {}", e);
+        }
+        print!("{}", tracer.profile());
+    }
+
+    if let Some((path, session)) = recording {
+        if let Err(e) = session.lock().unwrap().save(&path) {
+            eprintln!("{}: {}", path, e);
+        }
+    }
+}
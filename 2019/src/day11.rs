@@ -1,162 +1,76 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
-use std::collections::BTreeMap;
 
 use crate::intcode::*;
+use crate::intcode::device::Device;
+use aoc_common::canvas::Canvas;
+use aoc_common::ocr;
 
 #[aoc_generator(day11)]
 fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
     input.split(',').map(|l| l.parse()).collect()
 }
 
-#[aoc(day11, part1)]
-fn part1(program: &[isize]) -> usize {
-    let mut comp = IntCode::new(program);
-
-    let mut output_color = None;
-    let mut output_turn = None;
-
-    let mut panels = BTreeMap::new();
-
-    let directions = [
-        (0,-1), //up
-        (1,0), //right
-        (0,1), //down
-        (-1,0), //left
-    ];
-
-    let mut dir = 0; // up
-    let (mut x, mut y) = (0,0);
-
-    comp.run(
-        |action: CallbackAction| {
-            // println!("{:?} {},{} facing {}", action, x, y, dir);
-            match action {
-                CallbackAction::ReadInput => {
-                    Some(*panels.get(&(x,y)).unwrap_or(&0))
-                }
-                CallbackAction::WriteOutput(output) => {
-                    if output_color.is_none() {
-                        output_color = Some(output);
-                    } else {
-                        output_turn = Some(output);
-                    }
-
-                    if let (Some(color), Some(turn)) = (output_color, output_turn) {
-                        panels.insert((x,y), color);
-                        // print!("{},{} is now painted {}. Facing {}. turning {}.", x,y,color, dir, turn);
-
-                        if turn == 0 {
-                            dir += 3;
-                        } else {
-                            dir += 1;
-                        }
-                        dir %= 4;
-                        x += directions[dir].0;
-                        y += directions[dir].1;
-
-                        // println!(" Now at {},{} facing {}", x, y, dir);
-
-                        output_color = None;
-                        output_turn = None;
-                    }
-                    None
-                }
-            }
-        });
-
-    let min_x = panels.iter().map(|((x,_y),_c)| *x).min().unwrap();
-    let max_x = panels.iter().map(|((x,_y),_c)| *x).max().unwrap();
-    let min_y = panels.iter().map(|((_x,y),_c)| *y).min().unwrap();
-    let max_y = panels.iter().map(|((_x,y),_c)| *y).max().unwrap();
-
-    dbg!((min_x,max_x,min_y,max_y));
-
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            print!("{}", if *panels.get(&(x,y)).unwrap_or(&0) == 0 { ' ' } else { '#' });
-        }
-        println!();
+const DIRECTIONS : [(isize, isize); 4] = [
+    (0,-1), //up
+    (1,0), //right
+    (0,1), //down
+    (-1,0), //left
+];
+
+/// The robot's camera, paint arm and wheels: it reports the colour of the
+/// panel it's on, then takes a colour to paint followed by a turn.
+struct HullRobot {
+    panels: Canvas<isize>,
+    x: isize,
+    y: isize,
+    dir: usize,
+    color: Option<isize>,
+}
+
+impl Device for HullRobot {
+    fn input(&mut self) -> Option<isize> {
+        Some(self.panels.get(self.x, self.y))
     }
 
+    fn output(&mut self, value: isize) {
+        let color = match self.color.take() {
+            Some(color) => color,
+            None => {
+                self.color = Some(value);
+                return;
+            },
+        };
+
+        self.panels.set(self.x, self.y, color);
+        self.dir = (self.dir + if value == 0 { 3 } else { 1 }) % 4;
+        self.x += DIRECTIONS[self.dir].0;
+        self.y += DIRECTIONS[self.dir].1;
+    }
+}
+
+fn paint(program: &[isize], start_color: isize) -> Result<Canvas<isize>, IntCodeFault> {
+    let mut robot = HullRobot { panels: Canvas::new(), x: 0, y: 0, dir: 0, color: None };
+    robot.panels.set(0, 0, start_color);
+
+    IntCode::new(program).run_with(&mut robot)?;
+    Ok(robot.panels)
+}
+
+#[aoc(day11, part1)]
+fn part1(program: &[isize]) -> Result<usize, IntCodeFault> {
     // 9621 is too high
-    panels.len()
+    Ok(paint(program, 0)?.len())
 }
 
 #[aoc(day11, part2)]
-fn part2(program: &[isize]) -> usize {
-    let mut comp = IntCode::new(program);
-
-    let mut output_color = None;
-    let mut output_turn = None;
-
-    let mut panels = BTreeMap::new();
-
-    let directions = [
-        (0,-1), //up
-        (1,0), //right
-        (0,1), //down
-        (-1,0), //left
-    ];
-
-    let mut dir = 0; // up
-    let (mut x, mut y) = (0,0);
-
-    panels.insert((x,y), 1);
-
-    comp.run(
-        |action: CallbackAction| {
-            // println!("{:?} {},{} facing {}", action, x, y, dir);
-            match action {
-                CallbackAction::ReadInput => {
-                    Some(*panels.get(&(x,y)).unwrap_or(&0))
-                }
-                CallbackAction::WriteOutput(output) => {
-                    if output_color.is_none() {
-                        output_color = Some(output);
-                    } else {
-                        output_turn = Some(output);
-                    }
-
-                    if let (Some(color), Some(turn)) = (output_color, output_turn) {
-                        panels.insert((x,y), color);
-                        // print!("{},{} is now painted {}. Facing {}. turning {}.", x,y,color, dir, turn);
-
-                        if turn == 0 {
-                            dir += 3;
-                        } else {
-                            dir += 1;
-                        }
-                        dir %= 4;
-                        x += directions[dir].0;
-                        y += directions[dir].1;
-
-                        // println!(" Now at {},{} facing {}", x, y, dir);
-
-                        output_color = None;
-                        output_turn = None;
-                    }
-                    None
-                }
-            }
-        });
-
-    let min_x = panels.iter().map(|((x,_y),_c)| *x).min().unwrap();
-    let max_x = panels.iter().map(|((x,_y),_c)| *x).max().unwrap();
-    let min_y = panels.iter().map(|((_x,y),_c)| *y).min().unwrap();
-    let max_y = panels.iter().map(|((_x,y),_c)| *y).max().unwrap();
-
-    dbg!((min_x,max_x,min_y,max_y));
-
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            print!("{}", if *panels.get(&(x,y)).unwrap_or(&0) == 0 { ' ' } else { '#' });
-        }
-        println!();
+fn part2(program: &[isize]) -> Result<String, IntCodeFault> {
+    let panels = paint(program, 1)?;
+    let mut hull = Canvas::new();
+    for ((x, y), color) in panels.pixels() {
+        hull.set(x, y, color == 1);
     }
-
-    // 9621 is too high
-    panels.len()
+    Ok(ocr::read_canvas(&hull))
 }
 
 // #[cfg(test)]
@@ -1,409 +1,365 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
 
+use crate::intcode::*;
+use crate::intcode::device::Joystick;
+use crate::intcode::scanner::Scanner;
+use crate::intcode::screen::Screen;
+
 #[aoc_generator(day13)]
 fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
     input.split(',').map(|l| l.parse()).collect()
 }
 
-use std::collections::BTreeMap;
+const WALL : isize = 1;
+const BLOCK : isize = 2;
+const PADDLE : isize = 3;
+const BALL : isize = 4;
 
-struct IntCode {
-    memory: Vec<isize>,
-    relative_base: isize,
-    pc: usize,
-}
+/// Empty, wall, block, paddle and ball.
+const TILES : &str = " +X_*";
 
-#[derive(Debug)]
-enum CallbackAction {
-    ReadInput,
-    WriteOutput(isize),
+#[aoc(day13, part1)]
+fn part1(program: &[isize]) -> Result<usize, IntCodeFault> {
+    let mut comp = IntCode::new(program);
+
+    let mut screen = Screen::headless(TILES);
+    for output in comp.run_to_halt()? {
+        screen.write(output);
+    }
+    print!("{}", screen.render());
+
+    // 9621 is too high
+    Ok(screen.count(BLOCK))
 }
 
-impl IntCode {
+/// Steers the paddle: each time the game asks for input, returns -1, 0 or 1.
+trait BreakoutAgent {
+    fn joystick(&mut self, screen: &Screen) -> isize;
+}
 
-    fn new(initial: &[isize]) -> IntCode {
-        let mut memory : Vec<isize> = initial.to_vec();
-        memory.resize(memory.len()*100, 0);
-        IntCode {
-            memory,
-            relative_base: 0,
-            pc: 0,
-        }
+fn move_toward(screen: &Screen, target_x: isize) -> isize {
+    match screen.last_drawn(PADDLE) {
+        Some((paddle_x, _)) => (target_x - paddle_x).signum(),
+        None => 0,
     }
+}
 
-    fn get_value(&self, mode: isize, value: isize) -> isize {
-        match mode {
-            0 => self.memory[value as usize],
-            1 => value,
-            2 => self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
-        }
+/// Keeps the paddle under the ball.
+struct BallTracker;
+
+impl BreakoutAgent for BallTracker {
+    fn joystick(&mut self, screen: &Screen) -> isize {
+        screen.last_drawn(BALL).map_or(0, |(ball_x, _)| move_toward(screen, ball_x))
     }
+}
 
-    fn get_mut_ref(&mut self, mode: isize, value: isize) -> &mut isize {
-        match mode {
-            0 => &mut self.memory[value as usize],
-            1 => panic!("can't write to a constant"),
-            2 => &mut self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
+/// Heads for where a falling ball will reach the paddle's row, bouncing off
+/// walls on the way. Blocks can still deflect it, so the guess is refreshed
+/// every frame.
+#[derive(Default)]
+struct Predictor {
+    last_ball: Option<(isize, isize)>,
+}
+
+impl BreakoutAgent for Predictor {
+    fn joystick(&mut self, screen: &Screen) -> isize {
+        let (ball, paddle) = match (screen.last_drawn(BALL), screen.last_drawn(PADDLE)) {
+            (Some(ball), Some(paddle)) => (ball, paddle),
+            _ => return 0,
+        };
+        let last_ball = self.last_ball.replace(ball).unwrap_or(ball);
+
+        let (mut x, mut y) = ball;
+        let (mut dx, dy) = (ball.0 - last_ball.0, ball.1 - last_ball.1);
+        if dy <= 0 || dx == 0 {
+            return move_toward(screen, x);
         }
-    }
-    
-    fn run<F: FnMut(CallbackAction) -> Option<isize>>(&mut self, mut callback: F) -> () {
-        loop {
-            let mut instruction = self.memory[self.pc];
-            let opcode = instruction % 100; instruction /= 100;
-            let mode1 = instruction % 10; instruction /= 10;
-            let mode2 = instruction % 10; instruction /= 10;
-            let mode3 = instruction % 10; instruction /= 10;
-            assert_eq!(0, instruction);
-
-            match opcode {
-                99 => {
-                    return;
-                },
-                1 | 2 | 7 | 8 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-                    let out = self.memory[self.pc+3];
-                    let out = self.get_mut_ref(mode3, out);
-
-                    match opcode {
-                        1 => { *out = in1 + in2; }
-                        2 => { *out = in1 * in2; }
-                        7 => { *out = if in1 < in2 { 1 } else { 0 } }
-                        8 => { *out = if in1 == in2 { 1 } else { 0 } }
-                        _ => unreachable!(),
-                    }
-
-                    self.pc += 4;
-                },
-                3 => {
-                    let input = (callback)(CallbackAction::ReadInput).unwrap();
-                    let out = self.memory[self.pc+1];
-                    let out = self.get_mut_ref(mode1, out);
-
-                    *out = input;
-
-                    self.pc += 2;
-                },
-                4 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    assert_eq!(0, mode2);
-                    assert_eq!(0, mode3);
-                    let _ = (callback)(CallbackAction::WriteOutput(in1));
-
-                    self.pc += 2;
-                },
-                5 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-                    if in1 != 0 {
-                        self.pc = in2 as usize;
-                    } else {
-                        self.pc += 3;
-                    }
-                },
-                6 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-
-                    if in1 == 0 {
-                        self.pc = in2 as usize;
-                    } else {
-                        self.pc += 3;
-                    }
-                },
-                9 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-
-                    self.relative_base += in1;
-                    self.pc += 2;
-                }
-
-                _ => {
-                    panic!("Unexpected opcode {} at {}.", opcode, self.pc);
-                }
+        while y < paddle.1 - 1 {
+            if screen.get(x + dx, y + 1) == WALL {
+                dx = -dx;
             }
+            x += dx;
+            y += 1;
         }
+        move_toward(screen, x)
     }
 }
 
-#[derive(Clone,Copy,PartialEq)]
-enum Tile {
-    Empty,
-    Wall,
-    Block,
-    Paddle,
-    Ball
+/// Plays back a recorded game, then leaves the joystick centred.
+struct Replay {
+    inputs: std::vec::IntoIter<isize>,
 }
 
-impl Tile {
-    fn from(i: &isize) -> Tile {
-        match i {
-            0 => Tile::Empty,
-            1 => Tile::Wall,
-            2 => Tile::Block,
-            3 => Tile::Paddle,
-            4 => Tile::Ball,
-            i => panic!(format!("Unknown tile {}", i)),
-        }
+impl Replay {
+    fn new(inputs: Vec<isize>) -> Replay {
+        Replay { inputs: inputs.into_iter() }
+    }
+}
+
+impl BreakoutAgent for Replay {
+    fn joystick(&mut self, _screen: &Screen) -> isize {
+        self.inputs.next().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct GameStats {
+    /// How many times the game asked for input.
+    frames: usize,
+    blocks_broken: usize,
+    /// The most blocks broken in a single frame.
+    best_frame: usize,
+    score: isize,
+    won: bool,
+    /// Everything the agent sent, so the game can be replayed.
+    inputs: Vec<isize>,
+}
+
+impl GameStats {
+    fn blocks_per_frame(&self) -> f64 {
+        self.blocks_broken as f64 / self.frames.max(1) as f64
     }
 }
 
-impl std::fmt::Display for Tile {
+impl std::fmt::Display for GameStats {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{}", match self {
-            Tile::Empty => ' ',
-            Tile::Wall => '+',
-            Tile::Block => 'X',
-            Tile::Paddle => '_',
-            Tile::Ball => '*',
-        })
+        write!(f, "{} after {} frames, score {}, {} blocks broken ({:.3} per frame, at most {})",
+            if self.won { "won" } else { "lost" },
+            self.frames, self.score, self.blocks_broken, self.blocks_per_frame(), self.best_frame)
     }
 }
 
-#[aoc(day13, part1)]
-fn part1(program: &[isize]) -> usize {
-    let mut comp = IntCode::new(program);
+fn play(program: &[isize], agent: &mut dyn BreakoutAgent, screen: Screen) -> Result<GameStats, IntCodeFault> {
+    let mut program = program.to_vec();
+    program[0] = 2;
+    let mut comp = IntCode::new(&program);
 
-    let mut panels = BTreeMap::new();
-
-    let (mut x, mut y) = (None, None);
-
-    comp.run(
-        |action: CallbackAction| {
-            // println!("{:?} {},{} facing {}", action, x, y, dir);
-            match action {
-                CallbackAction::ReadInput => {
-                    unimplemented!();
-                }
-                CallbackAction::WriteOutput(output) => {
-                    if x.is_none() {
-                        x = Some(output);
-                    } else if y.is_none() {
-                        y = Some(output);
-                    } else {
-                        let tile = Tile::from(&output);
-
-                        *panels.entry((x.unwrap(),y.unwrap())).or_insert(tile) = tile;
-
-                        x = None;
-                        y = None;
-                    }
-                    None
-                }
-            }
-        });
+    let mut stats = GameStats::default();
+    let mut blocks = None;
+
+    let mut devices = (screen, Joystick::default());
+    while comp.run_with(&mut devices)? == RunState::NeedsInput {
+        let (screen, joystick) = &mut devices;
+        let remaining = screen.count(BLOCK);
+        let broken = blocks.map_or(0, |b : usize| b - remaining);
+        blocks = Some(remaining);
+        stats.blocks_broken += broken;
+        stats.best_frame = stats.best_frame.max(broken);
+        stats.frames += 1;
+
+        let paddle_move = agent.joystick(screen);
+        stats.inputs.push(paddle_move);
+        joystick.push(paddle_move);
+    }
+
+    let screen = devices.0;
+
+    stats.blocks_broken += blocks.unwrap_or(0) - screen.count(BLOCK);
+    stats.won = screen.count(BLOCK) == 0;
+    stats.score = screen.score().unwrap_or(0);
+    Ok(stats)
+}
+
+#[aoc(day13, part2)]
+fn part2(program: &[isize]) -> Result<isize, IntCodeFault> {
+    let stats = play(program, &mut BallTracker, Screen::live(TILES))?;
+    println!("Ball tracker {}", stats);
+    Ok(stats.score)
+}
 
-    let min_x = panels.iter().map(|((x,_y),_c)| *x).min().unwrap();
-    let max_x = panels.iter().map(|((x,_y),_c)| *x).max().unwrap();
-    let min_y = panels.iter().map(|((_x,y),_c)| *y).min().unwrap();
-    let max_y = panels.iter().map(|((_x,y),_c)| *y).max().unwrap();
+#[aoc(day13, part2, Predictor)]
+fn part2_predictor(program: &[isize]) -> Result<isize, IntCodeFault> {
+    let stats = play(program, &mut Predictor::default(), Screen::headless(TILES))?;
+    println!("Predictor {}", stats);
+    Ok(stats.score)
+}
+
+/// Records the predictor's game, then plays it back move for move.
+#[aoc(day13, part2, Replay)]
+fn part2_replay(program: &[isize]) -> Result<isize, IntCodeFault> {
+    let recorded = play(program, &mut Predictor::default(), Screen::headless(TILES))?;
+    let stats = play(program, &mut Replay::new(recorded.inputs), Screen::headless(TILES))?;
+    println!("Replay {}", stats);
+    Ok(stats.score)
+}
 
-    dbg!((min_x,max_x,min_y,max_y));
+/// Why the cheat couldn't finish the game.
+#[derive(Debug)]
+enum CheatError {
+    Fault(IntCodeFault),
+    /// The screen didn't show this tile, so there was nothing to scan for.
+    NotDrawn(&'static str),
+    /// The game ended before every value was narrowed down to one address.
+    NotFound,
+    /// The address found for the ball disagrees with the screen.
+    Mislocated { ball_x: usize },
+    /// The game ended with blocks still standing.
+    BlocksLeft(usize),
+}
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            print!("{}", panels.get(&(x,y)).unwrap_or(&Tile::Empty));
+impl std::fmt::Display for CheatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            CheatError::Fault(fault) => write!(f, "{}", fault),
+            CheatError::NotDrawn(what) => write!(f, "no {} on the screen", what),
+            CheatError::NotFound => write!(f, "the game ended before its memory was found"),
+            CheatError::Mislocated { ball_x } => write!(f, "[{}] doesn't hold the ball's x", ball_x),
+            CheatError::BlocksLeft(blocks) => write!(f, "the game ended with {} blocks left", blocks),
         }
-        println!();
     }
+}
 
-    // 9621 is too high
-    panels.values().filter(|v| **v == Tile::Block).count()
+impl std::error::Error for CheatError {}
+
+impl From<IntCodeFault> for CheatError {
+    fn from(fault: IntCodeFault) -> CheatError {
+        CheatError::Fault(fault)
+    }
 }
 
-use std::io::{Write, stdout};
-use crossterm::{ExecutableCommand, cursor};
+/// Where the game keeps its state, found by scanning memory between frames.
+struct GameMemory {
+    ball_x: usize,
+    paddle_x: usize,
+    score: usize,
+    /// Where the paddle's row starts in the game's row-major copy of the screen.
+    paddle_row: usize,
+}
 
-#[aoc(day13, part2)]
-fn part2(program: &[isize]) -> isize {
+/// Sends one joystick input and runs until the game wants the next one.
+fn frame(comp: &mut IntCode, screen: &mut Screen, joystick: Option<isize>) -> Result<RunState, IntCodeFault> {
+    comp.push_inputs(joystick.as_slice());
+    comp.run_with(screen)
+}
+
+/// Plays with the ball tracker, narrowing down each value every frame
+/// until only one address could hold it.
+fn locate(comp: &mut IntCode, screen: &mut Screen) -> Result<GameMemory, CheatError> {
+    let drawn = |screen: &Screen, tile, what| screen.last_drawn(tile).ok_or(CheatError::NotDrawn(what));
+
+    frame(comp, screen, None)?;
+    let (paddle_start, paddle_y) = drawn(screen, PADDLE, "paddle")?;
+
+    let mut ball_x = Scanner::new(comp);
+    let mut paddle_x = Scanner::new(comp);
+    let mut score = Scanner::new(comp);
+    let mut paddle_tile = Scanner::new(comp);
+
+    loop {
+        ball_x.equal(comp, drawn(screen, BALL, "ball")?.0);
+        paddle_x.equal(comp, drawn(screen, PADDLE, "paddle")?.0);
+        score.equal(comp, screen.score().unwrap_or(0));
+        paddle_tile.equal(comp, screen.get(paddle_start, paddle_y));
+
+        if let (Some(ball_x), Some(paddle_x), Some(score), Some(paddle_tile)) =
+            (ball_x.found(), paddle_x.found(), score.found(), paddle_tile.found()) {
+            let paddle_row = paddle_tile - paddle_start as usize;
+            return Ok(GameMemory { ball_x, paddle_x, score, paddle_row });
+        }
+
+        let joystick = BallTracker.joystick(screen);
+        if frame(comp, screen, Some(joystick))? == RunState::Halted {
+            return Err(CheatError::NotFound);
+        }
+    }
+}
+
+/// Finds the paddle's row in memory and walls it off, so the ball can never
+/// be missed and the game plays itself.
+#[aoc(day13, part2, Cheat)]
+fn part2_cheat(program: &[isize]) -> Result<isize, CheatError> {
     let mut program = program.to_vec();
     program[0] = 2;
-
     let mut comp = IntCode::new(&program);
+    let mut screen = Screen::headless(TILES);
 
-    let mut panels = BTreeMap::new();
-
-    let (mut x, mut y) = (None, None);
-
-    let mut ball_x = None;
-    let mut paddle_x = None;
-
-    let mut score = None;
-
-    let mut stdout = stdout();
-
-    let mut input_requested = false;
-
-    comp.run(
-        |action: CallbackAction| {
-            // println!("{:?} {},{} facing {}", action, x, y, dir);
-            match action {
-                CallbackAction::ReadInput => {
-                    input_requested = true;
-
-                    stdout
-                        .execute(cursor::SavePosition).unwrap()
-                        .execute(cursor::MoveTo(0, 21)).unwrap();
-
-                    let paddle_move = if let (Some(ball_x), Some(paddle_x)) = (ball_x, paddle_x) {
-                        print!("Because ball_x is {} and paddle_x is {}.", ball_x, paddle_x);
-
-                        if paddle_x < ball_x {
-                            Some(1)
-                        } else if paddle_x == ball_x {
-                            Some(0)
-                        } else {
-                            Some(-1)
-                        }
-                    } else {
-                        print!("Because ball_x or paddle_x is unknown.");
-                        Some(0)
-                    };
-                    println!(" Move is {:?}", paddle_move);
-
-                    stdout
-                        .execute(cursor::RestorePosition).unwrap();
-
-                    paddle_move
-                }
-                CallbackAction::WriteOutput(output) => {
-                    if x.is_none() {
-                        x = Some(output);
-                    } else if y.is_none() {
-                        y = Some(output);
-                    } else {
-                        let (tile_x, tile_y) = (x.unwrap(), y.unwrap());
-
-                        if (-1,0) == (tile_x, tile_y) {
-                            stdout
-                                .execute(cursor::SavePosition).unwrap()
-                                .execute(cursor::MoveTo(0, 20)).unwrap();
-                            score = Some(output);
-                            println!("Score is: {}", output);
-                            stdout.execute(cursor::RestorePosition).unwrap();
-
-                        } else {
-                            let tile = Tile::from(&output);
-
-                            if tile == Tile::Ball {
-                                ball_x = Some(tile_x);
-                            } else if tile == Tile::Paddle {
-                                paddle_x = Some(tile_x);
-                            }
-
-                            *panels.entry((tile_x, tile_y)).or_insert(tile) = tile;
-
-                            stdout
-                                .execute(cursor::SavePosition).unwrap()
-                                .execute(cursor::MoveTo(tile_x as u16, tile_y as u16)).unwrap();
-                            print!("{}", &tile);
-                            stdout.execute(cursor::RestorePosition).unwrap();
-
-                            if input_requested {
-                                // std::thread::sleep(std::time::Duration::from_millis(10));
-                            }
-                        }
-
-                        x = None;
-                        y = None;
-                    }
-                    None
-                }
-            }
-        });
+    let found = locate(&mut comp, &mut screen)?;
+    // Check the scan against the screen before patching anything.
+    if screen.last_drawn(BALL).map(|(x, _)| x) != Some(comp.peek(found.ball_x)) {
+        return Err(CheatError::Mislocated { ball_x: found.ball_x });
+    }
+
+    let width = screen.bounds().ok_or(CheatError::NotDrawn("wall"))?.width();
+    let paddle_x = comp.peek(found.paddle_x) as usize;
+    for x in 1..width - 1 {
+        if x != paddle_x {
+            comp.poke(found.paddle_row + x, WALL);
+        }
+    }
 
-    score.unwrap()
+    while frame(&mut comp, &mut screen, Some(0))? != RunState::Halted {}
+    match screen.count(BLOCK) {
+        0 => Ok(comp.peek(found.score)),
+        blocks => Err(CheatError::BlocksLeft(blocks)),
+    }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     fn test(start: &[isize], inputs: &[isize], expected: &[isize]) {
-//         let mut comp = IntCode::new(start, inputs);
-//         loop {
-//             if let Some(outputs) = comp.step() {
-//                 assert_eq!(expected, outputs.as_slice());
-//                 break;
-//             }
-//         }
-//     }
-
-//     #[test]
-//     fn part1_example() {
-//         test(
-//             &[109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99],
-//             &[], 
-//             &[109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99]);
-
-//         test(
-//             &[1102,34915192,34915192,7,4,7,99,0],
-//             &[],
-//             &[1219070632396864]
-//         );
-
-//         test(
-//             &[104,1125899906842624,99],
-//             &[],
-//             &[1125899906842624]
-//         );
-//     }
-
-//     #[test]
-//     fn part2_example() {
-//         let program = &[3,9,8,9,10,9,4,9,99,-1,8];
-//         test(program, &[9], &[0]);
-//         test(program, &[8], &[1]);
-//         test(program, &[7], &[0]);
-
-//         let program = &[3,9,7,9,10,9,4,9,99,-1,8];
-//         test(program, &[8], &[0]);
-//         test(program, &[8], &[0]);
-//         test(program, &[7], &[1]);
-//         test(program, &[6], &[1]);
-
-//         let program = &[3,3,1108,-1,8,3,4,3,99];
-//         test(program, &[9], &[0]);
-//         test(program, &[8], &[1]);
-//         test(program, &[7], &[0]);
-
-//         let program = &[3,3,1107,-1,8,3,4,3,99];
-//         test(program, &[8], &[0]);
-//         test(program, &[8], &[0]);
-//         test(program, &[7], &[1]);
-//         test(program, &[6], &[1]);
-//     }
-
-//     #[test]
-//     fn part2_example_jmp() {
-//         let program = &[3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9];
-//         test(program, &[0], &[0]);
-//         test(program, &[1], &[1]);
-//         test(program, &[2], &[1]);
-
-//         let program = &[3,3,1105,-1,9,1101,0,0,12,4,12,99,1];
-//         test(program, &[0], &[0]);
-//         test(program, &[1], &[1]);
-//         test(program, &[2], &[1]);
-
-//         let program = &[3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
-//         1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
-//         999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
-//         test(program, &[7], &[999]);
-//         test(program, &[8], &[1000]);
-//         test(program, &[9], &[1001]);
-
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::session::Session;
+
+    fn board(ball: (isize, isize)) -> Screen {
+        let mut screen = Screen::headless(TILES);
+        for y in 0..10 {
+            screen.set(0, y, WALL);
+            screen.set(6, y, WALL);
+        }
+        screen.set(3, 8, PADDLE);
+        screen.set(ball.0, ball.1, BALL);
+        screen
+    }
+
+    #[test]
+    fn part2_agents() {
+        // Falling to the right, the ball will land at x = 5.
+        let mut predictor = Predictor::default();
+        predictor.joystick(&board((1, 3)));
+        assert_eq!(1, predictor.joystick(&board((2, 4))));
+        assert_eq!(-1, BallTracker.joystick(&board((2, 4))));
+
+        // It bounces off the right wall and comes back to x = 3.
+        let mut predictor = Predictor::default();
+        predictor.joystick(&board((3, 3)));
+        assert_eq!(0, predictor.joystick(&board((4, 4))));
+        assert_eq!(1, BallTracker.joystick(&board((4, 4))));
+
+        let mut replay = Replay::new(vec![1, -1]);
+        let screen = board((0, 0));
+        assert_eq!(vec![1, -1, 0], (0..3).map(|_| replay.joystick(&screen)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn part2_replay() {
+        let program = parse_input(include_str!("../input/2019/day13.txt").trim()).unwrap();
+        let stats = play(&program, &mut Predictor::default(), Screen::headless(TILES)).unwrap();
+        assert!(stats.won);
+        let replayed = play(&program, &mut Replay::new(stats.inputs.clone()), Screen::headless(TILES)).unwrap();
+        assert_eq!(stats, replayed);
+    }
+
+    #[test]
+    fn part2_cheat_example() {
+        let program = parse_input(include_str!("../input/2019/day13.txt").trim()).unwrap();
+        assert_eq!(10547, part2_cheat(&program).unwrap());
+
+        // Nothing drawn, so there's nothing to scan for.
+        let error = locate(&mut IntCode::new(&[99]), &mut Screen::headless(TILES)).err().unwrap();
+        assert_eq!("no paddle on the screen", error.to_string());
+    }
+
+    #[test]
+    fn part2_session() {
+        // The predictor's moves on the real input and the final score.
+        let program = parse_input(include_str!("../input/2019/day13.txt").trim()).unwrap();
+        let session : Session = include_str!("../sessions/day13-part2.txt").parse().unwrap();
+        let stats = play(&program, &mut Predictor::default(), Screen::headless(TILES)).unwrap();
+        assert_eq!(stats.inputs, session.inputs());
+        assert_eq!(Some(10547), session.outputs().pop());
+
+        let mut program = program;
+        program[0] = 2;
+        assert!(session.replay_result(IntCode::new(&program)).is_ok());
+    }
+}
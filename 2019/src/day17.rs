@@ -1,9 +1,8 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
-use std::collections::{BTreeMap, BTreeSet};
-use std::collections::VecDeque;
 
 use crate::intcode::*;
+use crate::intcode::ascii::Ascii;
 
 #[aoc_generator(day17)]
 fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
@@ -11,38 +10,17 @@ fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
 }
 
 #[aoc(day17, part1)]
-fn part1(program: &[isize]) -> usize {
-    let (_rows, align_sum) = create_map(program);
-    align_sum
+fn part1(program: &[isize]) -> Result<usize, IntCodeFault> {
+    let (_rows, align_sum) = create_map(program)?;
+    Ok(align_sum)
 }
 
-fn create_map(program: &[isize]) -> (Vec<Vec<char>>, usize) {
-    let mut comp = IntCode::new(program);
-    let mut row = Vec::new();
-    let mut rows = Vec::new();
-    comp.run(
-        |action: CallbackAction| {
-            match action {
-                CallbackAction::ReadInput => {
-                    unimplemented!();
-                }
-                CallbackAction::WriteOutput(output) => {
-                    match output {
-                        10 => {
-                            if row.len() > 0 {
-                                rows.push(row.to_vec());
-                            }
-                            row.clear();
-                        }
-                        c => {
-                            row.push(c as u8 as char);
-                        },
-                    }
-
-                    None
-                }
-            }
-        });
+fn create_map(program: &[isize]) -> Result<(Vec<Vec<char>>, usize), IntCodeFault> {
+    let mut camera = Ascii::new(IntCode::new(program));
+    let mut rows : Vec<Vec<char>> = camera.run()?.lines.iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect())
+        .collect();
 
     let max_y = rows.len() - 1;
     let max_x = rows[0].len() - 1;
@@ -60,13 +38,7 @@ fn create_map(program: &[isize]) -> (Vec<Vec<char>>, usize) {
                 }
                 let xx = xx as usize;
                 let yy = yy as usize;
-                if xx > max_x || yy > max_y {
-                    false
-                } else if rows[yy as usize][xx as usize] != '#' {
-                    false
-                } else {
-                    true
-                }
+                xx <= max_x && yy <= max_y && rows[yy][xx] == '#'
             }) {
                 rows[y][x] = 'O';
                 align_sum += y*x;
@@ -83,16 +55,217 @@ fn create_map(program: &[isize]) -> (Vec<Vec<char>>, usize) {
         println!();
     }
     println!();
-    (rows, align_sum)
+    Ok((rows, align_sum))
 }
 
-#[aoc(day17, part2)]
-fn part2(program: &[isize]) -> usize {
-    let (rows, _align_sum) = create_map(program);
+/// Walks the scaffold from the robot, turning only when it can't go straight.
+fn trace_path(rows: &[Vec<char>]) -> Vec<String> {
+    let at = |x: isize, y: isize| {
+        if x < 0 || y < 0 {
+            return '.';
+        }
+        // Intersections are marked 'O' on the map, but they're still scaffold.
+        match rows.get(y as usize).and_then(|row| row.get(x as usize)) {
+            Some('O') => '#',
+            c => c.cloned().unwrap_or('.'),
+        }
+    };
+
+    let (mut x, mut y, robot) = rows.iter().enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &c)| (x as isize, y as isize, c)))
+        .find(|&(_, _, c)| "^v<>".contains(c))
+        .expect("no robot on the map");
+    let (mut dx, mut dy) = match robot {
+        '^' => (0, -1),
+        'v' => (0, 1),
+        '<' => (-1, 0),
+        _ => (1, 0),
+    };
+
+    let mut path = Vec::new();
+    loop {
+        // Turning left takes (dx,dy) to (dy,-dx), right to (-dy,dx).
+        let turn = if at(x + dy, y - dx) == '#' {
+            (dx, dy) = (dy, -dx);
+            'L'
+        } else if at(x - dy, y + dx) == '#' {
+            (dx, dy) = (-dy, dx);
+            'R'
+        } else {
+            return path;
+        };
+
+        let mut steps = 0;
+        while at(x + dx, y + dy) == '#' {
+            x += dx;
+            y += dy;
+            steps += 1;
+        }
+        path.push(format!("{},{}", turn, steps));
+    }
+}
+
+const MAX_ROUTINE : usize = 20;
+
+/// Splits the path into at most three movement functions, returning the main
+/// routine (as function indexes) and the functions.
+fn compress(path: &[String]) -> Option<(Vec<usize>, Vec<&[String]>)> {
+    fn search<'a>(path: &'a [String], main: &mut Vec<usize>, functions: &mut Vec<&'a [String]>) -> bool {
+        if path.is_empty() {
+            return true;
+        }
+        if main.len() * 2 + 1 > MAX_ROUTINE {
+            return false;
+        }
+
+        for f in 0..functions.len() {
+            if path.starts_with(functions[f]) {
+                main.push(f);
+                if search(&path[functions[f].len()..], main, functions) {
+                    return true;
+                }
+                main.pop();
+            }
+        }
+
+        if functions.len() < 3 {
+            for len in 1..=path.len() {
+                if path[..len].join(",").len() > MAX_ROUTINE {
+                    break;
+                }
+                main.push(functions.len());
+                functions.push(&path[..len]);
+                if search(&path[len..], main, functions) {
+                    return true;
+                }
+                functions.pop();
+                main.pop();
+            }
+        }
+        false
+    }
+
+    let mut main = Vec::new();
+    let mut functions = Vec::new();
+    if search(path, &mut main, &mut functions) {
+        Some((main, functions))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+enum RobotError {
+    Fault(IntCodeFault),
+    /// The path doesn't fit in three movement functions.
+    Uncompressible,
+}
+
+impl std::fmt::Display for RobotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            RobotError::Fault(fault) => write!(f, "{}", fault),
+            RobotError::Uncompressible => write!(f, "the path doesn't fit in three movement functions"),
+        }
+    }
+}
+
+impl std::error::Error for RobotError {}
+
+impl From<IntCodeFault> for RobotError {
+    fn from(fault: IntCodeFault) -> RobotError {
+        RobotError::Fault(fault)
+    }
+}
+
+/// The lines that program the robot to cover the whole scaffold: the main
+/// routine, three movement functions and no live video feed.
+fn movement_routines(program: &[isize]) -> Result<Vec<String>, RobotError> {
+    let (rows, _align_sum) = create_map(program)?;
+    let path = trace_path(&rows);
+    let (main, functions) = compress(&path).ok_or(RobotError::Uncompressible)?;
+
+    let main : Vec<String> = main.iter().map(|&f| ((b'A' + f as u8) as char).to_string()).collect();
+    let mut lines = vec![main.join(",")];
+    // The robot always asks for three functions and won't take an empty one.
+    for f in 0..3 {
+        lines.push(functions.get(f).unwrap_or(&functions[0]).join(","));
+    }
+    lines.push("n".to_string());
+    Ok(lines)
+}
 
+/// The program with the robot woken up, so it asks for its routines.
+fn wake_up(program: &[isize]) -> Vec<isize> {
     let mut program = program.to_vec();
     program[0] = 2;
-    let program = &program;
-    unimplemented!();
+    program
 }
 
+#[aoc(day17, part2)]
+fn part2(program: &[isize]) -> Result<isize, RobotError> {
+    let lines = movement_routines(program)?;
+
+    let mut robot = Ascii::new(IntCode::new(&wake_up(program)));
+    for line in &lines {
+        robot.send_line(line);
+    }
+
+    let transcript = robot.run()?;
+    // Stopping with only text means the robot fell off or was never woken.
+    Ok(transcript.value.ok_or_else(|| robot.comp().fault(FaultKind::NoOutput))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::session::Session;
+
+    const EXAMPLE : &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+
+    #[test]
+    fn part2_example() {
+        let mut rows : Vec<Vec<char>> = EXAMPLE.lines().map(|l| l.chars().collect()).collect();
+        // The path has to run straight through intersections.
+        rows[6][6] = 'O';
+        rows[10][8] = 'O';
+        let path = trace_path(&rows);
+        assert_eq!("R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2", path.join(","));
+
+        let (main, functions) = compress(&path).unwrap();
+        assert!(functions.len() <= 3);
+        assert!(functions.iter().all(|f| f.join(",").len() <= MAX_ROUTINE));
+        let expanded : Vec<String> = main.iter().flat_map(|&f| functions[f].iter().cloned()).collect();
+        assert_eq!(path, expanded);
+    }
+
+    #[test]
+    fn part2_session() {
+        // The inputs and final output of a part 2 run on the real input.
+        // Re-record it only if the robot's routines are meant to change.
+        let program = parse_input(include_str!("../input/2019/day17.txt").trim()).unwrap();
+        let session : Session = include_str!("../sessions/day17-part2.txt").parse().unwrap();
+
+        let sent : Vec<isize> = movement_routines(&program).unwrap().iter()
+            .flat_map(|line| line.bytes().chain(Some(b'\n')).map(isize::from))
+            .collect();
+        assert_eq!(sent, session.inputs());
+        assert_eq!(Some(&1_075_882), session.outputs().last());
+        assert!(session.replay_result(IntCode::new(&wake_up(&program))).is_ok());
+    }
+}
@@ -1,57 +1,66 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
 
+use crate::intcode::*;
+use crate::intcode::symbolic::{Expr, Symbolic, SymbolicError};
+
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Result<Vec<usize>, ParseIntError> {
+fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
     input.split(',').map(|l| l.parse()).collect()
 }
 
-fn run(memory: &mut [usize]) {
-    let mut i = 0;
-    while memory[i] != 99 {
-        let in1 = memory[i+1];
-        let in2 = memory[i+2];
-        let out = memory[i+3];
-        match memory[i] {
-            1 => {
-                memory[out] = memory[in1] + memory[in2];
-            },
-            2 => {
-                memory[out] = memory[in1] * memory[in2];
-            }
-            i => {
-                panic!("Unexpected opcode {}.", i);
-            }
-        }
-
-        i += 4;
-    }
+fn run(program: &[isize]) -> Result<IntCode, IntCodeFault> {
+    let mut comp = IntCode::new(program);
+    comp.run_to_halt()?;
+    Ok(comp)
 }
 
 #[aoc(day2, part1)]
-fn part1(input: &[usize]) -> usize {
-    let mut memory : Vec<usize> = input.to_vec();
+fn part1(input: &[isize]) -> Result<isize, IntCodeFault> {
+    let mut memory : Vec<isize> = input.to_vec();
     memory[1] = 12;
     memory[2] = 2;
-    run(memory.as_mut_slice());
-    memory[0]
+    Ok(run(&memory)?.memory().get(0))
 }
 
-#[aoc(day2, part2)]
-fn part2(input: &[usize]) -> usize {
+/// Works out `memory[0]` as a polynomial in the noun and verb.
+fn output_expr(program: &[isize]) -> Result<Expr, SymbolicError> {
+    let mut vm = Symbolic::new(program, &[(1, "noun"), (2, "verb")]);
+    vm.run()?;
+    vm.get(0).ok_or(SymbolicError::UnknownResult { addr: 0 })
+}
+
+/// Solves `output` for the verb that gives `target` at each noun.
+fn solve(output: &Expr, target: isize) -> Option<(isize, isize)> {
     for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut memory : Vec<usize> = input.to_vec();
-            memory[1] = noun;
-            memory[2] = verb;
-            run(memory.as_mut_slice());
-            if memory[0] == 19_690_720 {
-                return 100*noun + verb;
-            }
+        // Too big to fit means it can't be the target either.
+        let output = match output.substitute("noun", noun) {
+            Some(output) => output,
+            None => continue,
+        };
+        let verb = match output.linear_in("verb") {
+            Some((0, b)) => if b == target { Some(0) } else { None },
+            Some((a, b)) => target.checked_sub(b)
+                .filter(|d| d.checked_rem(a) == Some(0))
+                .and_then(|d| d.checked_div(a)),
+            None => (0..=99).find(|&verb| {
+                output.substitute("verb", verb).and_then(|e| e.as_constant()) == Some(target)
+            }),
+        };
+        if let Some(verb) = verb.filter(|verb| (0..=99).contains(verb)) {
+            return Some((noun, verb));
         }
     }
+    None
+}
 
-    unreachable!();
+#[aoc(day2, part2)]
+fn part2(input: &[isize]) -> Result<isize, SymbolicError> {
+    const TARGET : isize = 19_690_720;
+    let output = output_expr(input)?;
+    println!("memory[0] = {}", output);
+    let (noun, verb) = solve(&output, TARGET).ok_or(SymbolicError::NoSolution { target: TARGET })?;
+    Ok(100*noun + verb)
 }
 
 #[cfg(test)]
@@ -60,10 +69,9 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        let test = | start: &[usize], end: &[usize] | {
-            let mut mem : Vec<usize> = start.to_vec();
-            run(mem.as_mut_slice());
-            assert_eq!(end, mem.as_slice());
+        let test = | start: &[isize], end: &[isize] | {
+            let comp = run(start).unwrap();
+            assert_eq!(end, comp.memory().read(0..end.len()).as_slice());
         };
 
         test(&[1, 0, 0, 0, 99], &[2,0,0,0,99]);
@@ -73,5 +81,11 @@ mod tests {
 
     #[test]
     fn part2_example() {
+        // [0] = 100 * noun + verb
+        let program = &[1,0,0,3,1002,1,100,0,1,0,2,0,99];
+        let output = output_expr(program).unwrap();
+        assert_eq!("100*noun + verb", output.to_string());
+        assert_eq!(Some((12, 34)), solve(&output, 1234));
+        assert_eq!(None, solve(&output, 100_000));
     }
 }
@@ -6,7 +6,7 @@ use ndarray::Array2;
 #[aoc_generator(day24)]
 fn parse_input(input: &str) -> Result<Array2<char>, ndarray::ShapeError> {
     let rows : Vec<Vec<_>> = input.lines().map(|line| line.trim().chars().collect()).collect();
-    let cells : Vec<_> = rows.iter().map(|row| row.iter()).flatten().cloned().collect();
+    let cells : Vec<_> = rows.iter().flat_map(|row| row.iter()).cloned().collect();
     Array2::from_shape_vec((rows.len(), rows[0].len()), cells)
 }
 
@@ -79,7 +79,7 @@ fn part1(cells: &Array2<char>) -> usize {
 }
 
 #[aoc(day24, part2)]
-fn part2(program: &Array2<char>) -> isize {
+fn part2(_program: &Array2<char>) -> isize {
     unimplemented!();
 }
 
@@ -1,112 +1,33 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
 
+use crate::intcode::*;
+
 #[aoc_generator(day5)]
 fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
     input.split(',').map(|l| l.parse()).collect()
 }
 
-fn run(memory: &mut [isize], inputs: &[isize]) -> Vec<isize> {
-    let mut inputs = inputs.iter();
-    let mut outputs = Vec::new();
-    let mut i = 0;
-    loop {
-        let mut instruction = memory[i];
-        let opcode = instruction % 100; instruction /= 100;
-        let mode1 = instruction % 10; instruction /= 10;
-        let mode2 = instruction % 10; instruction /= 10;
-        let mode3 = instruction % 10; instruction /= 10;
-        assert_eq!(0, instruction);
-
-        match opcode {
-            99 => {
-                return outputs;
-            },
-            1 | 2 | 7 | 8=> {
-                let in1 = memory[i+1];
-                let in1 = if mode1 == 0 { memory[in1 as usize] } else { in1 };
-                let in2 = memory[i+2];
-                let in2 = if mode2 == 0 { memory[in2 as usize] } else { in2 };
-                let out = memory[i+3];
-                assert_eq!(0, mode3);
-
-                match opcode {
-                    1 => { memory[out as usize] = in1 + in2; }
-                    2 => { memory[out as usize] = in1 * in2; }
-                    7 => { memory[out as usize] = if in1 < in2 { 1 } else { 0 } }
-                    8 => { memory[out as usize] = if in1 == in2 { 1 } else { 0 } }
-                    _ => unreachable!(),
-                }
-
-                i += 4;
-            },
-            3 => {
-                assert_eq!(0, mode1);
-                assert_eq!(0, mode2);
-                assert_eq!(0, mode3);
-
-                let out = memory[i+1];
-                memory[out as usize] = *inputs.next().expect("needed an input.");
-
-                i += 2;
-            },
-            4 => {
-                let in1 = memory[i+1];
-                let in1 = if mode1 == 0 { memory[in1 as usize] } else { in1 };
-                assert_eq!(0, mode2);
-                assert_eq!(0, mode3);
-                outputs.push(in1);
-
-                i += 2;
-            },
-            5 => {
-                let in1 = memory[i+1];
-                let in1 = if mode1 == 0 { memory[in1 as usize] } else { in1 };
-                let in2 = memory[i+2];
-                let in2 = if mode2 == 0 { memory[in2 as usize] } else { in2 };
-
-                if in1 != 0 {
-                    i = in2 as usize;
-                } else {
-                    i += 3;
-                }
-            },
-            6 => {
-                let in1 = memory[i+1];
-                let in1 = if mode1 == 0 { memory[in1 as usize] } else { in1 };
-                let in2 = memory[i+2];
-                let in2 = if mode2 == 0 { memory[in2 as usize] } else { in2 };
-
-                if in1 == 0 {
-                    i = in2 as usize;
-                } else {
-                    i += 3;
-                }
-            },
-
-            _ => {
-                panic!("Unexpected opcode {} at {}.", opcode, i);
-            }
-        }
-    }
+fn run(program: &[isize], inputs: &[isize]) -> Result<(IntCode, Vec<isize>), IntCodeFault> {
+    let mut comp = IntCode::with_inputs(program, inputs);
+    let outputs = comp.run_to_halt()?;
+    Ok((comp, outputs))
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &[isize]) -> isize {
-    let mut memory : Vec<isize> = input.to_vec();
-    let outputs = run(memory.as_mut_slice(), &[1]);
-    for i in 0..(outputs.len()-2) {
-        assert_eq!(0, outputs[i]);
+fn part1(input: &[isize]) -> Result<isize, IntCodeFault> {
+    let (_comp, outputs) = run(input, &[1])?;
+    for output in &outputs[..outputs.len()-2] {
+        assert_eq!(0, *output);
     }
-    *outputs.last().unwrap()
+    Ok(*outputs.last().unwrap())
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &[isize]) -> isize {
-    let mut memory : Vec<isize> = input.to_vec();
-    let outputs = run(memory.as_mut_slice(), &[5]);
+fn part2(input: &[isize]) -> Result<isize, IntCodeFault> {
+    let (_comp, outputs) = run(input, &[5])?;
     assert_eq!(1, outputs.len());
-    outputs[0]
+    Ok(outputs[0])
 }
 
 #[cfg(test)]
@@ -114,10 +35,9 @@ mod tests {
     use super::*;
 
     fn test(start: &[isize], inputs: &[isize], end: Option<&[isize]>, expected: &[isize]) {
-        let mut mem : Vec<isize> = start.to_vec();
-        let outputs = run(mem.as_mut_slice(), inputs);
+        let (comp, outputs) = run(start, inputs).unwrap();
         if let Some(end) = end {
-            assert_eq!(end, mem.as_slice());
+            assert_eq!(end, comp.memory().read(0..end.len()).as_slice());
         }
         assert_eq!(expected, outputs.as_slice());
     }
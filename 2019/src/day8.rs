@@ -1,6 +1,9 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
 
+use aoc_common::canvas::Canvas;
+use aoc_common::ocr;
+
 #[aoc_generator(day8)]
 fn parse_input(input: &str) -> Result<Vec<usize>, ParseIntError> {
     Ok(input.chars().map(|c| (c as usize) - ('0' as usize) ).collect())
@@ -61,10 +64,7 @@ impl std::fmt::Display for Color {
     }
 }
 
-#[aoc(day8, part2)]
-fn part2(input: &[usize]) -> usize {
-    let width = 25;
-    let height = 6;
+fn decode(input: &[usize], width: usize, height: usize) -> Canvas<bool> {
     let layers = input.len() / (width * height);
     assert_eq!(input.len(), width*height*layers);
     let layers = input.chunks(width*height);
@@ -76,25 +76,31 @@ fn part2(input: &[usize]) -> usize {
         }
     }
 
+    let mut image = Canvas::new();
     for h in 0..height {
         for w in 0..width {
-            print!("{}", picture[h*width + w]);
+            image.set(w as isize, h as isize, picture[h*width + w] == Color::White);
         }
-        println!();
     }
+    image
+}
 
-    println!();
-
-    0
+#[aoc(day8, part2)]
+fn part2(input: &[usize]) -> String {
+    ocr::read_canvas(&decode(input, 25, 6))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn part1_example() {
     }
 
     #[test]
     fn part2_example() {
+        let image = decode(&[0,2,2,2,1,1,2,2,2,2,1,2,0,0,0,0], 2, 2);
+        assert_eq!("P1\n2 2\n0 1\n1 0\n", image.to_pbm());
     }
 }
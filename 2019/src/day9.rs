@@ -1,167 +1,27 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::num::ParseIntError;
 
+use crate::intcode::*;
+
 #[aoc_generator(day9)]
 fn parse_input(input: &str) -> Result<Vec<isize>, ParseIntError> {
     input.split(',').map(|l| l.parse()).collect()
 }
 
-use std::collections::VecDeque;
-
-struct IntCode {
-    memory: Vec<isize>,
-    inputs: VecDeque<isize>,
-    outputs: Vec<isize>,
-    relative_base: isize,
-    pc: usize,
-}
-
-impl IntCode {
-
-    fn new(initial: &[isize], inputs: &[isize]) -> IntCode {
-        let mut memory : Vec<isize> = initial.to_vec();
-        memory.resize(memory.len()*100, 0);
-        IntCode {
-            memory,
-            inputs: inputs.iter().cloned().collect(),
-            outputs: Vec::new(),
-            relative_base: 0,
-            pc: 0,
-        }
-    }
-
-    fn get_value(&self, mode: isize, value: isize) -> isize {
-        match mode {
-            0 => self.memory[value as usize],
-            1 => value,
-            2 => self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
-        }
-    }
-
-    fn get_mut_ref(&mut self, mode: isize, value: isize) -> &mut isize {
-        match mode {
-            0 => &mut self.memory[value as usize],
-            1 => panic!("can't write to a constant"),
-            2 => &mut self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
-        }
-    }
-    
-    fn step(&mut self) -> Option<Vec<isize>> {
-        let mut instruction = self.memory[self.pc];
-        let opcode = instruction % 100; instruction /= 100;
-        let mode1 = instruction % 10; instruction /= 10;
-        let mode2 = instruction % 10; instruction /= 10;
-        let mode3 = instruction % 10; instruction /= 10;
-        assert_eq!(0, instruction);
-
-        match opcode {
-            99 => {
-                Some(self.outputs.clone())
-            },
-            1 | 2 | 7 | 8 => {
-                let in1 = self.memory[self.pc+1];
-                let in1 = self.get_value(mode1, in1);
-                let in2 = self.memory[self.pc+2];
-                let in2 = self.get_value(mode2, in2);
-                let out = self.memory[self.pc+3];
-                let out = self.get_mut_ref(mode3, out);
-
-                match opcode {
-                    1 => { *out = in1 + in2; }
-                    2 => { *out = in1 * in2; }
-                    7 => { *out = if in1 < in2 { 1 } else { 0 } }
-                    8 => { *out = if in1 == in2 { 1 } else { 0 } }
-                    _ => unreachable!(),
-                }
-
-                self.pc += 4;
-                None
-            },
-            3 => {
-                let input = self.inputs.pop_front().expect("needed an input.");
-                let out = self.memory[self.pc+1];
-                let out = self.get_mut_ref(mode1, out);
-
-                *out = input;
-
-                self.pc += 2;
-                None
-            },
-            4 => {
-                let in1 = self.memory[self.pc+1];
-                let in1 = self.get_value(mode1, in1);
-                assert_eq!(0, mode2);
-                assert_eq!(0, mode3);
-                self.outputs.push(in1);
-
-                self.pc += 2;
-                None
-            },
-            5 => {
-                let in1 = self.memory[self.pc+1];
-                let in1 = self.get_value(mode1, in1);
-                let in2 = self.memory[self.pc+2];
-                let in2 = self.get_value(mode2, in2);
-                if in1 != 0 {
-                    self.pc = in2 as usize;
-                } else {
-                    self.pc += 3;
-                }
-                None
-            },
-            6 => {
-                let in1 = self.memory[self.pc+1];
-                let in1 = self.get_value(mode1, in1);
-                let in2 = self.memory[self.pc+2];
-                let in2 = self.get_value(mode2, in2);
-
-                if in1 == 0 {
-                    self.pc = in2 as usize;
-                } else {
-                    self.pc += 3;
-                }
-                None
-            },
-            9 => {
-                let in1 = self.memory[self.pc+1];
-                let in1 = self.get_value(mode1, in1);
-
-                self.relative_base += in1;
-                self.pc += 2;
-                None
-            }
-
-            _ => {
-                panic!("Unexpected opcode {} at {}.", opcode, self.pc);
-            }
-        }
-    }
-}
-
 #[aoc(day9, part1)]
-fn part1(program: &[isize]) -> isize {
-    let mut comp = IntCode::new(program, &[1]);
-    loop {
-        // dbg!(comp.pc);
-        if let Some(outputs) = comp.step() {
-            assert_eq!(1, outputs.len());
-            return outputs[0];
-        }
-    }
+fn part1(program: &[isize]) -> Result<isize, IntCodeFault> {
+    let mut comp = IntCode::with_inputs(program, &[1]);
+    let outputs = comp.run_to_halt()?;
+    assert_eq!(1, outputs.len());
+    Ok(outputs[0])
 }
 
 #[aoc(day9, part2)]
-fn part2(program: &[isize]) -> isize {
-    let mut comp = IntCode::new(program, &[2]);
-    loop {
-        // dbg!(comp.pc);
-        if let Some(outputs) = comp.step() {
-            assert_eq!(1, outputs.len());
-            return outputs[0];
-        }
-    }
+fn part2(program: &[isize]) -> Result<isize, IntCodeFault> {
+    let mut comp = IntCode::with_inputs(program, &[2]);
+    let outputs = comp.run_to_halt()?;
+    assert_eq!(1, outputs.len());
+    Ok(outputs[0])
 }
 
 #[cfg(test)]
@@ -169,13 +29,8 @@ mod tests {
     use super::*;
 
     fn test(start: &[isize], inputs: &[isize], expected: &[isize]) {
-        let mut comp = IntCode::new(start, inputs);
-        loop {
-            if let Some(outputs) = comp.step() {
-                assert_eq!(expected, outputs.as_slice());
-                break;
-            }
-        }
+        let mut comp = IntCode::with_inputs(start, inputs);
+        assert_eq!(expected, comp.run_to_halt().unwrap());
     }
 
     #[test]
@@ -198,6 +53,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn part1_wide_example() {
+        use num_bigint::BigInt;
+        use crate::intcode::word::Word;
+
+        fn test_wide<W: Word>(start: &[isize], expected: &[isize]) {
+            let start : Vec<W> = start.iter().map(|&w| W::from_isize(w)).collect();
+            let expected : Vec<W> = expected.iter().map(|&w| W::from_isize(w)).collect();
+            let mut comp = IntCode::from_words(&start);
+            comp.enable_overflow_checks();
+            assert_eq!(expected, comp.run_to_halt().unwrap());
+        }
+
+        for &(start, expected) in &[
+            (&[1102,34915192,34915192,7,4,7,99,0][..], &[1219070632396864][..]),
+            (&[104,1125899906842624,99], &[1125899906842624]),
+        ] {
+            test_wide::<i64>(start, expected);
+            test_wide::<i128>(start, expected);
+            test_wide::<BigInt>(start, expected);
+        }
+    }
+
     #[test]
     fn part2_example() {
         let program = &[3,9,8,9,10,9,4,9,99,-1,8];
@@ -1,130 +1,778 @@
-pub struct IntCode {
-    memory: Vec<isize>,
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::fmt;
+
+pub mod ascii;
+pub mod asm;
+pub mod batch;
+pub mod cfg;
+pub mod debugger;
+pub mod device;
+pub mod disasm;
+pub mod explore;
+pub mod memory;
+pub mod pipeline;
+pub mod scanner;
+pub mod screen;
+pub mod session;
+pub mod symbolic;
+pub mod trace;
+pub mod translate;
+pub mod word;
+
+use self::disasm::Line;
+use self::memory::{Memory, MemoryUsage};
+use self::session::{Event, Session};
+use self::trace::Tracer;
+use self::translate::{Op, Translation};
+use self::word::Word;
+
+/// Cloning a machine is cheap: memory pages are shared copy-on-write, so
+/// search-based solvers can fork at every branch point instead of backtracking.
+///
+/// Memory holds `isize` words unless the machine is built with
+/// `from_words` from another `Word` type, such as `i128` or `BigInt`.
+#[derive(Clone)]
+pub struct IntCode<W: Word = isize> {
+    memory: Memory<W>,
     relative_base: isize,
     pc: usize,
+    inputs: VecDeque<W>,
+    tracer: Option<Arc<Mutex<Tracer>>>,
+    recorder: Option<Arc<Mutex<Session<W>>>>,
+    translation: Option<Translation<W>>,
+    image_len: usize,
+    overflow_checks: bool,
 }
 
 #[derive(Debug)]
-pub enum CallbackAction {
+pub enum CallbackAction<W = isize> {
     ReadInput,
-    WriteOutput(isize),
+    WriteOutput(W),
+}
+
+/// What went wrong when the VM couldn't execute an instruction.
+/// Parameters are numbered from 1, as in the mode digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    UnknownOpcode,
+    UnknownMode { param: usize },
+    LeftoverMode { param: usize },
+    WriteToImmediate,
+    NegativeAddress(isize),
+    /// An add or multiply that doesn't fit in a word (only reported with
+    /// overflow checks on), or an address that doesn't fit in an `isize`.
+    Overflow,
+    /// The caller expected the program to run on, but it wanted an input.
+    NeedsInput,
+    /// The caller expected an output, but the program halted.
+    NoOutput,
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultKind::UnknownOpcode => write!(f, "unknown opcode"),
+            FaultKind::UnknownMode { param } => write!(f, "unknown mode for parameter {}", param),
+            FaultKind::LeftoverMode { param } => write!(f, "non-zero mode digit for missing parameter {}", param),
+            FaultKind::WriteToImmediate => write!(f, "can't write to a constant"),
+            FaultKind::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            FaultKind::Overflow => write!(f, "overflow"),
+            FaultKind::NeedsInput => write!(f, "needed an input but none was queued"),
+            FaultKind::NoOutput => write!(f, "halted without an output"),
+        }
+    }
+}
+
+/// A fault along with the machine state needed to diagnose it. Instruction
+/// words too wide for an `isize` are saturated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntCodeFault {
+    pub kind: FaultKind,
+    pub pc: usize,
+    pub instruction: isize,
+    pub relative_base: isize,
+}
+
+impl fmt::Display for IntCodeFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at pc {} (instruction {}, relative base {})",
+            self.kind, self.pc, self.instruction, self.relative_base)
+    }
+}
+
+impl std::error::Error for IntCodeFault {}
+
+/// Why a call to `resume` gave control back to the caller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState<W = isize> {
+    Halted,
+    NeedsInput,
+    Output(W),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_code(code: isize) -> Option<Mode> {
+        match code {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> isize {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    pub fn from_code(code: isize) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Multiply),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustRelativeBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> isize {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn parameter_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Whether the last parameter is an address that gets written to.
+    pub fn writes(self) -> bool {
+        matches!(self, Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals | Opcode::Input)
+    }
+}
+
+/// A decoded instruction word: the opcode plus the mode of each parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    /// Decodes `word`, rejecting unknown opcodes and modes, mode digits
+    /// for parameters the opcode doesn't have, and immediate-mode writes.
+    pub fn decode(word: isize) -> Result<Instruction, FaultKind> {
+        if word < 0 {
+            return Err(FaultKind::UnknownOpcode);
+        }
+
+        let opcode = Opcode::from_code(word % 100).ok_or(FaultKind::UnknownOpcode)?;
+        let mut digits = word / 100;
+        let mut modes = [Mode::Position; 3];
+        for (i, mode) in modes.iter_mut().enumerate() {
+            let code = digits % 10; digits /= 10;
+            if i >= opcode.parameter_count() && code != 0 {
+                return Err(FaultKind::LeftoverMode { param: i + 1 });
+            }
+            *mode = Mode::from_code(code).ok_or(FaultKind::UnknownMode { param: i + 1 })?;
+        }
+
+        if digits != 0 {
+            return Err(FaultKind::LeftoverMode { param: 4 });
+        }
+
+        if opcode.writes() && modes[opcode.parameter_count() - 1] == Mode::Immediate {
+            return Err(FaultKind::WriteToImmediate);
+        }
+
+        Ok(Instruction { opcode, modes })
+    }
+
+    pub fn encode(&self) -> isize {
+        self.modes.iter().rev().fold(0, |word, mode| word * 10 + mode.code()) * 100 + self.opcode.code()
+    }
+
+    /// Number of memory words the instruction occupies, including itself.
+    pub fn width(&self) -> usize {
+        1 + self.opcode.parameter_count()
+    }
+}
+
+/// The address `offset` words past `base`, faulting if it overflows or is
+/// negative. Shared with the symbolic VM so both resolve addresses alike.
+pub(crate) fn offset_address(base: isize, offset: isize) -> Result<usize, FaultKind> {
+    let addr = base.checked_add(offset).ok_or(FaultKind::Overflow)?;
+    if addr < 0 {
+        Err(FaultKind::NegativeAddress(addr))
+    } else {
+        Ok(addr as usize)
+    }
 }
 
 impl IntCode {
 
     pub fn new(initial: &[isize]) -> IntCode {
-        let mut memory : Vec<isize> = initial.to_vec();
-        memory.resize(memory.len()*100, 0);
+        IntCode::from_words(initial)
+    }
+
+    pub fn with_inputs(initial: &[isize], inputs: &[isize]) -> IntCode {
+        let mut comp = IntCode::from_words(initial);
+        comp.push_inputs(inputs);
+        comp
+    }
+}
+
+impl<W: Word> IntCode<W> {
+
+    pub fn from_words(initial: &[W]) -> IntCode<W> {
         IntCode {
-            memory,
+            memory: Memory::new(initial),
             relative_base: 0,
             pc: 0,
+            inputs: VecDeque::new(),
+            tracer: None,
+            recorder: None,
+            translation: None,
+            image_len: initial.len(),
+            overflow_checks: false,
         }
     }
 
-    fn get_value(&self, mode: isize, value: isize) -> isize {
-        match mode {
-            0 => self.memory[value as usize],
-            1 => value,
-            2 => self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
+    pub fn memory(&self) -> &Memory<W> {
+        &self.memory
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory.usage()
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    /// Records every instruction executed from now on, including by forks.
+    pub fn set_tracer(&mut self, tracer: Arc<Mutex<Tracer>>) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Records every input consumed and output produced from now on,
+    /// including by forks.
+    pub fn set_recorder(&mut self, recorder: Arc<Mutex<Session<W>>>) {
+        self.recorder = Some(recorder);
+    }
+
+    fn record(&self, event: Event<W>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.lock().unwrap().record(event);
+        }
+    }
+
+    /// Executes from a pre-decoded copy of the program instead of decoding
+    /// every instruction as it's reached. Results are identical either way.
+    /// Only the initial image is translated; code anywhere else is decoded
+    /// as it's reached.
+    pub fn enable_translation(&mut self) {
+        self.translation = Some(Translation::new(&self.memory, self.image_len));
+    }
+
+    pub fn translation(&self) -> Option<&Translation<W>> {
+        self.translation.as_ref()
+    }
+
+    /// Faults with `Overflow` instead of wrapping when an add or multiply
+    /// doesn't fit in a word, so the pc of the overflow is known.
+    pub fn enable_overflow_checks(&mut self) {
+        self.overflow_checks = true;
+    }
+
+    /// Reads memory between resumes without running anything.
+    pub fn peek(&self, addr: usize) -> W {
+        self.memory.get(addr)
+    }
+
+    /// Patches memory between resumes. Patching code is fine: any translated
+    /// copy of it is discarded, just as when the program writes there itself.
+    pub fn poke(&mut self, addr: usize, value: W) {
+        *self.memory.get_mut(addr) = value;
+        if let Some(translation) = &mut self.translation {
+            translation.invalidate(addr);
         }
     }
 
-    fn get_mut_ref(&mut self, mode: isize, value: isize) -> &mut isize {
+    pub fn is_halted(&self) -> bool {
+        self.memory.get(self.pc).to_isize() == Some(99)
+    }
+
+    pub fn push_input(&mut self, input: W) {
+        self.inputs.push_back(input);
+    }
+
+    pub fn push_inputs(&mut self, inputs: &[W]) {
+        self.inputs.extend(inputs.iter().cloned());
+    }
+
+    pub fn pending_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// The address `offset` words past `base`.
+    fn address(&self, base: isize, offset: &W) -> Result<usize, FaultKind> {
+        offset_address(base, offset.to_isize().ok_or(FaultKind::Overflow)?)
+    }
+
+    fn get_value(&self, mode: Mode, value: &W) -> Result<W, FaultKind> {
         match mode {
-            0 => &mut self.memory[value as usize],
-            1 => panic!("can't write to a constant"),
-            2 => &mut self.memory[(self.relative_base + value) as usize],
-            i => panic!("unimplemented mode {}", i)
+            Mode::Position => Ok(self.memory.get(self.address(0, value)?)),
+            Mode::Immediate => Ok(value.clone()),
+            Mode::Relative => Ok(self.memory.get(self.address(self.relative_base, value)?)),
         }
     }
-    
-    pub fn run<F: FnMut(CallbackAction) -> Option<isize>>(&mut self, mut callback: F) -> () {
+
+    fn set_value(&mut self, mode: Mode, value: &W, result: W) -> Result<(), FaultKind> {
+        let addr = match mode {
+            Mode::Position => self.address(0, value)?,
+            Mode::Immediate => return Err(FaultKind::WriteToImmediate),
+            Mode::Relative => self.address(self.relative_base, value)?,
+        };
+        self.poke(addr, result);
+        Ok(())
+    }
+
+    /// Decodes the instruction at the pc along with its parameters.
+    fn fetch(&mut self) -> Result<Op<W>, FaultKind> {
+        match &mut self.translation {
+            Some(translation) => translation.get(&self.memory, self.pc),
+            None => Op::decode(&self.memory, self.pc),
+        }
+    }
+
+    fn jump(&mut self, target: &W) -> Result<(), FaultKind> {
+        self.pc = self.address(0, target)?;
+        Ok(())
+    }
+
+    /// A fault of `kind` at the current pc, also for callers that find the
+    /// program misbehaving in a way the VM itself can't see.
+    pub fn fault(&self, kind: FaultKind) -> IntCodeFault {
+        IntCodeFault {
+            kind,
+            pc: self.pc,
+            instruction: self.memory.get(self.pc).saturating_to_isize(),
+            relative_base: self.relative_base,
+        }
+    }
+
+    /// Executes a single instruction, reading from the input queue.
+    /// Returns `None` if the program can keep going without the caller.
+    /// On `NeedsInput` the pc is left on the input instruction so that it
+    /// is retried once an input has been pushed.
+    pub fn step(&mut self) -> Result<Option<RunState<W>>, IntCodeFault> {
+        if let Some(tracer) = self.tracer.clone() {
+            let line = Line::decode_at(&self.memory, self.pc);
+            let relative_base = self.relative_base;
+            let state = self.execute().map_err(|kind| self.fault(kind))?;
+            if !matches!(state, Some(RunState::NeedsInput)) {
+                tracer.lock().unwrap().record(&line, relative_base);
+            }
+            return Ok(state);
+        }
+        self.execute().map_err(|kind| self.fault(kind))
+    }
+
+    fn execute(&mut self) -> Result<Option<RunState<W>>, FaultKind> {
+        let Op { instruction, params } = self.fetch()?;
+        let opcode = instruction.opcode;
+        let [mode1, mode2, mode3] = instruction.modes;
+        let [param1, param2, param3] = params;
+
+        match opcode {
+            Opcode::Halt => {
+                return Ok(Some(RunState::Halted));
+            },
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                let in1 = self.get_value(mode1, &param1)?;
+                let in2 = self.get_value(mode2, &param2)?;
+
+                let out = match opcode {
+                    Opcode::Add if self.overflow_checks => in1.checked_add(&in2).ok_or(FaultKind::Overflow)?,
+                    Opcode::Add => in1.wrapping_add(&in2),
+                    Opcode::Multiply if self.overflow_checks => in1.checked_mul(&in2).ok_or(FaultKind::Overflow)?,
+                    Opcode::Multiply => in1.wrapping_mul(&in2),
+                    Opcode::LessThan => W::from_isize(if in1 < in2 { 1 } else { 0 }),
+                    Opcode::Equals => W::from_isize(if in1 == in2 { 1 } else { 0 }),
+                    _ => unreachable!(),
+                };
+                self.set_value(mode3, &param3, out)?;
+
+                self.pc += 4;
+            },
+            Opcode::Input => {
+                let input = match self.inputs.front() {
+                    Some(input) => input.clone(),
+                    None => return Ok(Some(RunState::NeedsInput)),
+                };
+
+                self.set_value(mode1, &param1, input.clone())?;
+                self.inputs.pop_front();
+                self.record(Event::Input(input));
+                self.pc += 2;
+            },
+            Opcode::Output => {
+                let in1 = self.get_value(mode1, &param1)?;
+                self.pc += 2;
+                self.record(Event::Output(in1.clone()));
+                return Ok(Some(RunState::Output(in1)));
+            },
+            Opcode::JumpIfTrue => {
+                let in1 = self.get_value(mode1, &param1)?;
+                let in2 = self.get_value(mode2, &param2)?;
+                if !in1.is_zero() {
+                    self.jump(&in2)?;
+                } else {
+                    self.pc += 3;
+                }
+            },
+            Opcode::JumpIfFalse => {
+                let in1 = self.get_value(mode1, &param1)?;
+                let in2 = self.get_value(mode2, &param2)?;
+
+                if in1.is_zero() {
+                    self.jump(&in2)?;
+                } else {
+                    self.pc += 3;
+                }
+            },
+            Opcode::AdjustRelativeBase => {
+                let in1 = self.get_value(mode1, &param1)?;
+
+                self.relative_base = in1.to_isize()
+                    .and_then(|offset| self.relative_base.checked_add(offset))
+                    .ok_or(FaultKind::Overflow)?;
+                self.pc += 2;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs until the program halts, needs an input, or produces an output.
+    pub fn resume(&mut self) -> Result<RunState<W>, IntCodeFault> {
         loop {
-            let mut instruction = self.memory[self.pc];
-            let opcode = instruction % 100; instruction /= 100;
-            let mode1 = instruction % 10; instruction /= 10;
-            let mode2 = instruction % 10; instruction /= 10;
-            let mode3 = instruction % 10; instruction /= 10;
-            assert_eq!(0, instruction);
-
-            match opcode {
-                99 => {
-                    return;
-                },
-                1 | 2 | 7 | 8 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-                    let out = self.memory[self.pc+3];
-                    let out = self.get_mut_ref(mode3, out);
-
-                    match opcode {
-                        1 => { *out = in1 + in2; }
-                        2 => { *out = in1 * in2; }
-                        7 => { *out = if in1 < in2 { 1 } else { 0 } }
-                        8 => { *out = if in1 == in2 { 1 } else { 0 } }
-                        _ => unreachable!(),
-                    }
+            if let Some(state) = self.step()? {
+                return Ok(state);
+            }
+        }
+    }
 
-                    self.pc += 4;
-                },
-                3 => {
-                    if let Some(input) = (callback)(CallbackAction::ReadInput) {
-                        let out = self.memory[self.pc+1];
-                        let out = self.get_mut_ref(mode1, out);
-                        *out = input;
-                        self.pc += 2;
-                    } else {
-                        return;
-                    }
-                },
-                4 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    assert_eq!(0, mode2);
-                    assert_eq!(0, mode3);
-                    let _ = (callback)(CallbackAction::WriteOutput(in1));
-
-                    self.pc += 2;
-                },
-                5 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-                    if in1 != 0 {
-                        self.pc = in2 as usize;
-                    } else {
-                        self.pc += 3;
-                    }
-                },
-                6 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-                    let in2 = self.memory[self.pc+2];
-                    let in2 = self.get_value(mode2, in2);
-
-                    if in1 == 0 {
-                        self.pc = in2 as usize;
-                    } else {
-                        self.pc += 3;
+    /// Like `resume`, but also gives control back (with `None`) as soon as
+    /// `stop` returns true before an instruction.
+    pub fn run_until<P: FnMut(&IntCode<W>) -> bool>(&mut self, mut stop: P) -> Result<Option<RunState<W>>, IntCodeFault> {
+        loop {
+            if stop(self) {
+                return Ok(None);
+            }
+
+            if let Some(state) = self.step()? {
+                return Ok(Some(state));
+            }
+        }
+    }
+
+    /// Resumes and expects the program to produce an output.
+    pub fn expect_output(&mut self) -> Result<W, IntCodeFault> {
+        match self.resume()? {
+            RunState::Output(output) => Ok(output),
+            RunState::NeedsInput => Err(self.fault(FaultKind::NeedsInput)),
+            RunState::Halted => Err(self.fault(FaultKind::NoOutput)),
+        }
+    }
+
+    /// Iterates over outputs until the program halts, needs an input or faults.
+    pub fn outputs(&mut self) -> Outputs<'_, W> {
+        Outputs { comp: self, done: false }
+    }
+
+    /// Runs the program to completion from the input queue and returns everything it output.
+    pub fn run_to_halt(&mut self) -> Result<Vec<W>, IntCodeFault> {
+        let outputs = self.outputs().collect::<Result<_, _>>()?;
+        if !self.is_halted() {
+            return Err(self.fault(FaultKind::NeedsInput));
+        }
+        Ok(outputs)
+    }
+
+    pub fn run<F: FnMut(CallbackAction<W>) -> Option<W>>(&mut self, mut callback: F) -> Result<(), IntCodeFault> {
+        loop {
+            match self.resume()? {
+                RunState::Halted => return Ok(()),
+                RunState::NeedsInput => {
+                    match (callback)(CallbackAction::ReadInput) {
+                        Some(input) => self.push_input(input),
+                        None => return Ok(()),
                     }
                 },
-                9 => {
-                    let in1 = self.memory[self.pc+1];
-                    let in1 = self.get_value(mode1, in1);
-
-                    self.relative_base += in1;
-                    self.pc += 2;
+                RunState::Output(output) => {
+                    let _ = (callback)(CallbackAction::WriteOutput(output));
                 }
+            }
+        }
+    }
+}
+
+pub struct Outputs<'a, W: Word = isize> {
+    comp: &'a mut IntCode<W>,
+    done: bool,
+}
+
+impl<'a, W: Word> Iterator for Outputs<'a, W> {
+    type Item = Result<W, IntCodeFault>;
+
+    fn next(&mut self) -> Option<Result<W, IntCodeFault>> {
+        if self.done {
+            return None;
+        }
+
+        match self.comp.resume() {
+            Ok(RunState::Output(output)) => Some(Ok(output)),
+            Ok(RunState::Halted) | Ok(RunState::NeedsInput) => None,
+            Err(fault) => {
+                self.done = true;
+                Some(Err(fault))
+            }
+        }
+    }
+}
 
-                _ => {
-                    panic!("Unexpected opcode {} at {}.", opcode, self.pc);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_example() {
+        let mut comp = IntCode::with_inputs(&[3,9,8,9,10,9,4,9,99,-1,8], &[8]);
+        assert_eq!(Ok(None), comp.step());
+        assert_eq!(2, comp.pc());
+        assert_eq!(8, comp.memory().get(9));
+        assert_eq!(Ok(RunState::Output(1)), comp.resume());
+        assert_eq!(Ok(RunState::Halted), comp.resume());
+        assert!(comp.is_halted());
+    }
+
+    #[test]
+    fn resume_example() {
+        let mut comp = IntCode::new(&[3,9,8,9,10,9,4,9,99,-1,8]);
+        assert_eq!(Ok(RunState::NeedsInput), comp.resume());
+        assert_eq!(0, comp.pc());
+
+        comp.push_inputs(&[7, 8]);
+        assert_eq!(Ok(None), comp.run_until(|c| c.pc() == 6));
+        assert_eq!(1, comp.pending_inputs());
+        assert_eq!(Ok(vec![0]), comp.run_to_halt());
+    }
+
+    #[test]
+    fn outputs_example() {
+        let program = &[3,0,4,0,3,0,4,0,99];
+        let mut comp = IntCode::with_inputs(program, &[5]);
+        assert_eq!(Ok(vec![5]), comp.outputs().collect::<Result<Vec<_>, _>>());
+        assert!(!comp.is_halted());
+
+        comp.push_input(6);
+        assert_eq!(Ok(6), comp.expect_output());
+        assert_eq!(Ok(RunState::Halted), comp.resume());
+    }
+
+    #[test]
+    fn run_example() {
+        let program = &[109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+        let mut outputs = Vec::new();
+        let mut comp = IntCode::new(program);
+        comp.run(|action| {
+            match action {
+                CallbackAction::ReadInput => unreachable!(),
+                CallbackAction::WriteOutput(output) => {
+                    outputs.push(output);
+                    None
                 }
             }
+        }).unwrap();
+        assert_eq!(program, outputs.as_slice());
+    }
+
+    #[test]
+    fn sparse_memory_example() {
+        let mut comp = IntCode::new(&[1101,5,6,1000000,4,1000000,99]);
+        assert_eq!(Ok(vec![11]), comp.run_to_halt());
+        assert_eq!(11, comp.memory().get(1000000));
+        assert_eq!(2, comp.memory_usage().pages);
+    }
+
+    #[test]
+    fn fork_example() {
+        let program = &[3,9,8,9,10,9,4,9,99,-1,8];
+        let mut comp = IntCode::new(program);
+        assert_eq!(Ok(RunState::NeedsInput), comp.resume());
+
+        let mut fork = comp.clone();
+        assert_eq!(1, comp.memory_usage().shared_pages);
+
+        comp.push_input(8);
+        fork.push_input(7);
+        assert_eq!(Ok(vec![1]), comp.run_to_halt());
+        assert_eq!(Ok(vec![0]), fork.run_to_halt());
+        assert_eq!((1, 0), (comp.memory().get(9), fork.memory().get(9)));
+    }
+
+    #[test]
+    fn fault_example() {
+        let fault = |program: &[isize]| IntCode::new(program).run_to_halt().unwrap_err();
+
+        assert_eq!(
+            IntCodeFault { kind: FaultKind::UnknownOpcode, pc: 4, instruction: 42, relative_base: 0 },
+            fault(&[1101,1,1,0,42]));
+        assert_eq!(FaultKind::UnknownMode { param: 2 }, fault(&[3101,1,1,0,99]).kind);
+        assert_eq!(FaultKind::LeftoverMode { param: 2 }, fault(&[1104,1,99]).kind);
+        assert_eq!(FaultKind::LeftoverMode { param: 4 }, fault(&[100001,0,0,0,99]).kind);
+        assert_eq!(FaultKind::WriteToImmediate, fault(&[11101,1,1,0,99]).kind);
+        assert_eq!(FaultKind::NegativeAddress(-1), fault(&[4,-1,99]).kind);
+        assert_eq!(FaultKind::NegativeAddress(-3), fault(&[1105,1,-3]).kind);
+
+        let fault = fault(&[109,-5,204,2,99]);
+        assert_eq!(FaultKind::NegativeAddress(-3), fault.kind);
+        assert_eq!((2, 204, -5), (fault.pc, fault.instruction, fault.relative_base));
+        assert_eq!("negative address -3 at pc 2 (instruction 204, relative base -5)", fault.to_string());
+
+        let fault = IntCode::new(&[4,0,3,0,99]).run_to_halt().unwrap_err();
+        assert_eq!((FaultKind::NeedsInput, 2, 3), (fault.kind, fault.pc, fault.instruction));
+        let mut comp = IntCode::new(&[104,1,99]);
+        assert_eq!(Ok(1), comp.expect_output());
+        assert_eq!(FaultKind::NoOutput, comp.expect_output().unwrap_err().kind);
+    }
+
+    #[test]
+    fn translation_example() {
+        let run = |program: &[isize], translate: bool| {
+            let mut comp = IntCode::new(program);
+            if translate {
+                comp.enable_translation();
+            }
+            (comp.run_to_halt(), comp.translation().map_or(0, |t| t.invalidations()))
+        };
+
+        // Increments the operand of its own output instruction until it reaches 3.
+        let counter = &[104,0,1001,1,1,1,1008,1,3,20,1006,20,0,99];
+        assert_eq!((Ok(vec![0, 1, 2]), 0), run(counter, false));
+        assert_eq!((Ok(vec![0, 1, 2]), 3), run(counter, true));
+
+        // Overwrites an output instruction with a halt before reaching it.
+        let halts = &[1101,99,0,6,104,7,104,8];
+        assert_eq!(run(halts, false).0, run(halts, true).0);
+        assert_eq!(Ok(vec![7]), run(halts, true).0);
+
+        let quine = &[109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+        assert_eq!(run(quine, false).0, run(quine, true).0);
+
+        // Forks share the translation until one of them rewrites its code.
+        let mut comp = IntCode::new(counter);
+        comp.enable_translation();
+        assert_eq!(counter.len(), comp.translation().unwrap().image_len());
+        let mut fork = comp.clone();
+        assert_eq!(1, comp.translation().unwrap().shared_pages());
+        assert_eq!(Ok(vec![0, 1, 2]), fork.run_to_halt());
+        assert_eq!((0, 3), (comp.translation().unwrap().shared_pages(), fork.translation().unwrap().invalidations()));
+        assert_eq!((Ok(vec![0, 1, 2]), 3), (comp.run_to_halt(), comp.translation().unwrap().invalidations()));
+    }
+
+    #[test]
+    fn overflow_example() {
+        use num_bigint::BigInt;
+
+        // Squares x n times, then outputs it.
+        let squarer = |x: isize, n: isize| asm::assemble(&format!("
+            loop:   MUL [x], [x] -> [x]
+                    ADD [n], #-1 -> [n]
+                    JNZ [n], #loop
+                    OUT [x]
+                    HLT
+            x:      DATA {}
+            n:      DATA {}
+        ", x, n)).unwrap();
+
+        fn run<W: Word>(program: &[isize], checked: bool) -> Result<Vec<W>, IntCodeFault> {
+            let program : Vec<W> = program.iter().map(|&w| W::from_isize(w)).collect();
+            let mut comp = IntCode::from_words(&program);
+            if checked {
+                comp.enable_overflow_checks();
+            }
+            comp.run_to_halt()
         }
+
+        let program = squarer(34915192, 2);
+        assert!(run::<isize>(&program, false).is_ok());
+        let fault = run::<i64>(&program, true).unwrap_err();
+        assert_eq!((FaultKind::Overflow, 0, 2), (fault.kind, fault.pc, fault.instruction));
+        assert_eq!(Ok(vec![1486133206772489918753597034496]), run::<i128>(&program, true));
+
+        let program = squarer(34915192, 3);
+        assert_eq!(FaultKind::Overflow, run::<i128>(&program, true).unwrap_err().kind);
+        let expected : BigInt = "2208591908271884275716666044391205095685624913057165413974016".parse().unwrap();
+        assert_eq!(Ok(vec![expected]), run::<BigInt>(&program, true));
+
+        // Addresses still have to fit in an isize, and so do reported instructions.
+        let fault = IntCode::<i128>::from_words(&[4, 1 << 70, 99]).run_to_halt().unwrap_err();
+        assert_eq!((FaultKind::Overflow, 0), (fault.kind, fault.pc));
+        let fault = IntCode::<i128>::from_words(&[1 << 70]).run_to_halt().unwrap_err();
+        assert_eq!((FaultKind::UnknownOpcode, isize::MAX), (fault.kind, fault.instruction));
     }
-}
\ No newline at end of file
+}
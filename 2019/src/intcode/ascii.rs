@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+use super::{IntCode, IntCodeFault, RunState};
+use super::device::Device;
+
+/// What an ASCII program printed before it stopped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    /// Text output split on newlines. An unterminated last line is kept.
+    pub lines: Vec<String>,
+    /// The last output that wasn't ASCII, usually the puzzle answer.
+    pub value: Option<isize>,
+    pub halted: bool,
+}
+
+/// A text terminal device: types the lines it's sent and collects what the
+/// program prints.
+#[derive(Clone, Debug, Default)]
+pub struct Terminal {
+    keys: VecDeque<isize>,
+    line: String,
+    printed: Transcript,
+}
+
+impl Terminal {
+
+    pub fn new() -> Terminal {
+        Terminal::default()
+    }
+
+    /// Queues `line` followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        self.keys.extend(line.bytes().chain(Some(b'\n')).map(isize::from));
+    }
+
+    /// Everything printed since the last call, including an unfinished line.
+    pub fn take_transcript(&mut self) -> Transcript {
+        if !self.line.is_empty() {
+            self.printed.lines.push(std::mem::take(&mut self.line));
+        }
+        std::mem::take(&mut self.printed)
+    }
+}
+
+impl Device for Terminal {
+    fn input(&mut self) -> Option<isize> {
+        self.keys.pop_front()
+    }
+
+    fn output(&mut self, value: isize) {
+        match value {
+            10 => self.printed.lines.push(std::mem::take(&mut self.line)),
+            c if (0..128).contains(&c) => self.line.push(c as u8 as char),
+            value => self.printed.value = Some(value),
+        }
+    }
+}
+
+/// Drives an IntCode program that talks in lines of ASCII text.
+pub struct Ascii {
+    comp: IntCode,
+    terminal: Terminal,
+}
+
+impl Ascii {
+
+    pub fn new(comp: IntCode) -> Ascii {
+        Ascii { comp, terminal: Terminal::new() }
+    }
+
+    pub fn comp(&self) -> &IntCode {
+        &self.comp
+    }
+
+    /// Queues `line` followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        self.terminal.send_line(line);
+    }
+
+    /// Runs until the program halts or needs more input than has been sent.
+    pub fn run(&mut self) -> Result<Transcript, IntCodeFault> {
+        let state = self.comp.run_with(&mut self.terminal)?;
+        let mut transcript = self.terminal.take_transcript();
+        transcript.halted = state == RunState::Halted;
+        Ok(transcript)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_example() {
+        // Prints "Hi", echoes one character, then outputs 1000.
+        let mut ascii = Ascii::new(IntCode::new(&[104,72,104,105,104,10,3,100,4,100,104,1000,99]));
+
+        let expected = Transcript { lines: vec!["Hi".to_string()], value: None, halted: false };
+        assert_eq!(Ok(expected), ascii.run());
+
+        ascii.send_line("x");
+        let expected = Transcript { lines: vec!["x".to_string()], value: Some(1000), halted: true };
+        assert_eq!(Ok(expected), ascii.run());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Instruction, Mode, Opcode};
+
+const OPCODES : &[Opcode] = &[
+    Opcode::Add,
+    Opcode::Multiply,
+    Opcode::Input,
+    Opcode::Output,
+    Opcode::JumpIfTrue,
+    Opcode::JumpIfFalse,
+    Opcode::LessThan,
+    Opcode::Equals,
+    Opcode::AdjustRelativeBase,
+    Opcode::Halt,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Number(isize),
+    Label(String),
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let s = s.trim();
+    if let Ok(n) = s.parse() {
+        Ok(Value::Number(n))
+    } else if is_identifier(s) {
+        Ok(Value::Label(s.to_string()))
+    } else {
+        Err(format!("expected a number or label but found '{}'", s))
+    }
+}
+
+fn parse_operand(s: &str) -> Result<(Mode, Value), String> {
+    let s = s.trim();
+    if let Some(value) = s.strip_prefix('#') {
+        Ok((Mode::Immediate, parse_value(value)?))
+    } else if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let inner = inner.trim();
+        if let Some(offset) = inner.strip_prefix("rb") {
+            let offset = offset.replace(' ', "");
+            let offset = offset.strip_prefix('+').unwrap_or(&offset);
+            let offset = if offset.is_empty() { Value::Number(0) } else { parse_value(offset)? };
+            Ok((Mode::Relative, offset))
+        } else {
+            Ok((Mode::Position, parse_value(inner)?))
+        }
+    } else {
+        Err(format!("expected '#value', '[addr]' or '[rb+offset]' but found '{}'", s))
+    }
+}
+
+pub fn parse_opcode(mnemonic: &str) -> Option<Opcode> {
+    OPCODES.iter().cloned().find(|o| o.mnemonic().eq_ignore_ascii_case(mnemonic))
+}
+
+/// Parses the body of one line (after any labels) into the words it emits.
+fn parse_words(text: &str) -> Result<Vec<Value>, String> {
+    let (mnemonic, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text, ""),
+    };
+
+    if mnemonic.eq_ignore_ascii_case("DATA") {
+        return rest.split(',').map(parse_value).collect();
+    }
+
+    let opcode = parse_opcode(mnemonic)
+        .ok_or_else(|| format!("unknown mnemonic '{}'", mnemonic))?;
+
+    let rest = rest.replace("->", ",");
+    let operands : Vec<&str> = rest.split(',').map(|o| o.trim()).filter(|o| !o.is_empty()).collect();
+    if operands.len() != opcode.parameter_count() {
+        return Err(format!("{} takes {} operands but found {}",
+            opcode.mnemonic(), opcode.parameter_count(), operands.len()));
+    }
+
+    let mut modes = [Mode::Position; 3];
+    let mut words = vec![Value::Number(0)];
+    for (i, operand) in operands.iter().enumerate() {
+        let (mode, value) = parse_operand(operand)?;
+        modes[i] = mode;
+        words.push(value);
+    }
+
+    if opcode.writes() && modes[opcode.parameter_count() - 1] == Mode::Immediate {
+        return Err(format!("{} can't write to an immediate operand", opcode.mnemonic()));
+    }
+
+    words[0] = Value::Number(Instruction { opcode, modes }.encode());
+    Ok(words)
+}
+
+/// Assembles a listing in the format `disasm` produces into a program.
+///
+/// Each line holds an optional address prefix (`0012:`, checked against the
+/// assembled address), any number of `label:` definitions, and then either
+/// an instruction such as `ADD [rb+3], #5 -> [100]` or `DATA 1, 2, label`.
+/// Labels can be used anywhere a number can; `;` starts a comment.
+pub fn assemble(source: &str) -> Result<Vec<isize>, AsmError> {
+    let mut labels = BTreeMap::new();
+    let mut words = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message| AsmError { line: i + 1, message };
+        let mut text = line.split(';').next().unwrap().trim();
+
+        while let Some(colon) = text.find(':') {
+            let name = text[..colon].trim();
+            if let Ok(addr) = name.parse::<usize>() {
+                if addr != words.len() {
+                    return Err(error(format!("address {} doesn't match assembled address {}", addr, words.len())));
+                }
+            } else if is_identifier(name) {
+                if labels.insert(name.to_string(), words.len()).is_some() {
+                    return Err(error(format!("label '{}' is defined twice", name)));
+                }
+            } else {
+                return Err(error(format!("'{}' is not a valid label", name)));
+            }
+            text = text[colon+1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        for word in parse_words(text).map_err(error)? {
+            words.push((i + 1, word));
+        }
+    }
+
+    words.into_iter()
+        .map(|(line, word)| match word {
+            Value::Number(n) => Ok(n),
+            Value::Label(name) => labels.get(&name)
+                .map(|addr| *addr as isize)
+                .ok_or_else(|| AsmError { line, message: format!("undefined label '{}'", name) }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::disasm::disassemble;
+    use crate::intcode::IntCode;
+
+    #[test]
+    fn assemble_example() {
+        let program = assemble("
+            ; count down from the input to one
+                    IN -> [counter]
+            loop:   OUT [counter]
+                    ADD [counter], #-1 -> [counter]
+                    JNZ [counter], #loop
+                    HLT
+            counter: DATA 0
+        ").unwrap();
+        assert_eq!(vec![3,12,4,12,1001,12,-1,12,1005,12,2,99,0], program);
+        assert_eq!(Ok(vec![3,2,1]), IntCode::with_inputs(&program, &[3]).run_to_halt());
+
+        let program = assemble("ARB #1\nOUT [rb-1]\nADD [rb], #5 -> [rb+2]\nHLT").unwrap();
+        assert_eq!(vec![109,1,204,-1,21201,0,5,2,99], program);
+    }
+
+    #[test]
+    fn round_trip_example() {
+        let programs : &[&[isize]] = &[
+            &[109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99],
+            &[1102,34915192,34915192,7,4,7,99,0],
+            &[3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9],
+            &[3,3,1105,-1,9,1101,0,0,12,4,12,99,1],
+            &[3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
+            1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
+            999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99],
+            &[3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5],
+            &[12345,1105,1,2,11101,-7],
+        ];
+
+        for program in programs {
+            let listing = disassemble(program).to_string();
+            assert_eq!(program.to_vec(), assemble(&listing).unwrap(), "{}", listing);
+        }
+    }
+
+    #[test]
+    fn error_example() {
+        assert_eq!(
+            Err(AsmError { line: 2, message: "undefined label 'nowhere'".to_string() }),
+            assemble("IN -> [0]\nJZ [0], #nowhere"));
+        assert_eq!(
+            Err(AsmError { line: 1, message: "ADD can't write to an immediate operand".to_string() }),
+            assemble("ADD #1, #2 -> #3"));
+        assert_eq!(
+            Err(AsmError { line: 2, message: "address 1 doesn't match assembled address 2".to_string() }),
+            assemble("0000: IN -> [0]\n0001: HLT"));
+    }
+}
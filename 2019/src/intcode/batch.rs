@@ -0,0 +1,137 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam_utils::thread;
+
+use super::{IntCode, IntCodeFault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchError {
+    pub job: usize,
+    pub fault: IntCodeFault,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job {}: {}", self.job, self.fault)
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+fn default_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Applies `f` to every item on a pool of `workers` threads, which take the
+/// next unclaimed item whenever they finish one. Results are in item order.
+pub fn par_map<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results : Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let handles : Vec<_> = (0..workers.clamp(1, items.len().max(1)))
+            .map(|_| scope.spawn(|_| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    match items.get(i) {
+                        Some(item) => done.push((i, f(item))),
+                        None => return done,
+                    }
+                }
+            }))
+            .collect();
+
+        for handle in handles {
+            for (i, result) in handle.join().unwrap() {
+                results[i] = Some(result);
+            }
+        }
+    }).unwrap();
+
+    results.into_iter().map(|r| r.unwrap()).collect()
+}
+
+/// Many short runs of the same program with different inputs, such as
+/// probing every point of a scan. Every job starts from a clone of one
+/// loaded machine, so the program's pages are shared rather than copied.
+#[derive(Clone)]
+pub struct Batch {
+    image: IntCode,
+    jobs: Vec<Vec<isize>>,
+    workers: usize,
+}
+
+impl Batch {
+
+    pub fn new(program: &[isize]) -> Batch {
+        Batch { image: IntCode::new(program), jobs: Vec::new(), workers: default_workers() }
+    }
+
+    /// Uses `workers` threads instead of one per CPU.
+    pub fn workers(mut self, workers: usize) -> Batch {
+        self.workers = workers;
+        self
+    }
+
+    /// Queues a run with `inputs` and returns its index in the results.
+    pub fn job(&mut self, inputs: &[isize]) -> usize {
+        self.jobs.push(inputs.to_vec());
+        self.jobs.len() - 1
+    }
+
+    /// Runs every job until it halts and returns what each one output, in
+    /// job order. A job that wants more input than it was given fails with
+    /// `FaultKind::NeedsInput`, so a cut-short run is never mistaken for a
+    /// finished one.
+    pub fn run(&self) -> Result<Vec<Vec<isize>>, BatchError> {
+        let results = par_map(&self.jobs, self.workers, |inputs| {
+            let mut comp = self.image.clone();
+            comp.push_inputs(inputs);
+            comp.run_to_halt()
+        });
+
+        results.into_iter()
+            .enumerate()
+            .map(|(job, result)| result.map_err(|fault| BatchError { job, fault }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::FaultKind;
+
+    #[test]
+    fn batch_example() {
+        // Outputs whether the input is less than 8.
+        let mut batch = Batch::new(&[3,9,7,9,10,9,4,9,99,-1,8]).workers(3);
+        for input in 0..20 {
+            assert_eq!(input as usize, batch.job(&[input]));
+        }
+        let expected : Vec<Vec<isize>> = (0..20).map(|i| vec![if i < 8 { 1 } else { 0 }]).collect();
+        assert_eq!(Ok(expected), batch.run());
+
+        // A job without enough input is an error, not a short result.
+        let mut batch = Batch::new(&[3,0,4,0,3,0,4,0,99]);
+        batch.job(&[6, 7]);
+        assert_eq!(Ok(vec![vec![6, 7]]), batch.run());
+        batch.job(&[5]);
+        let error = batch.run().unwrap_err();
+        assert_eq!((1, FaultKind::NeedsInput, 4), (error.job, error.fault.kind, error.fault.pc));
+
+        // Reading through the input as an address faults on negative ones.
+        let mut batch = Batch::new(&[3,3,4,0,99]);
+        batch.job(&[1]);
+        batch.job(&[-2]);
+        let error = batch.run().unwrap_err();
+        assert_eq!((1, FaultKind::NegativeAddress(-2)), (error.job, error.fault.kind));
+    }
+}
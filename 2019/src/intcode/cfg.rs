@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::{Instruction, Mode, Opcode};
+use super::disasm::Line;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// Execution runs on into the next block.
+    Fallthrough(usize),
+    Jump(usize),
+    /// A jump whose target is read from memory, so it isn't known statically.
+    Indirect,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub edges: Vec<Edge>,
+}
+
+/// What a recursive-descent pass over a program's image can tell without
+/// running it.
+///
+/// Code is found by following jumps from address 0. Jumps through memory,
+/// such as returns, can't be followed, so a constant that an ADD or MUL
+/// builds from immediate operands is also treated as code if it lands on a
+/// valid instruction: that's how compiled IntCode pushes return addresses.
+pub struct Analysis {
+    pub lines: BTreeMap<usize, Line>,
+    pub blocks: BTreeMap<usize, Block>,
+    /// Ranges of the image that no reachable instruction covers.
+    pub unreachable: Vec<Range<usize>>,
+    /// Reachable writes to fixed addresses inside reachable code, as
+    /// `(pc, address written)`.
+    pub self_modifying: Vec<(usize, usize)>,
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+    /// Reachable addresses that don't decode as an instruction, so running
+    /// into them faults. They aren't counted as code.
+    pub invalid_targets: Vec<usize>,
+    pub indirect_jumps: Vec<usize>,
+}
+
+fn successors(addr: usize, instruction: &Instruction, params: &[isize]) -> Vec<Edge> {
+    let next = addr + instruction.width();
+    match instruction.opcode {
+        Opcode::Halt => Vec::new(),
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            let jump = match instruction.modes[1] {
+                Mode::Immediate if params[1] >= 0 => Edge::Jump(params[1] as usize),
+                Mode::Immediate => return Vec::new(), // always faults
+                _ => Edge::Indirect,
+            };
+            if instruction.modes[0] == Mode::Immediate {
+                let taken = (params[0] != 0) == (instruction.opcode == Opcode::JumpIfTrue);
+                vec![if taken { jump } else { Edge::Fallthrough(next) }]
+            } else {
+                vec![jump, Edge::Fallthrough(next)]
+            }
+        },
+        _ => vec![Edge::Fallthrough(next)],
+    }
+}
+
+/// The constant an all-immediate ADD or MUL produces, or `None` if it
+/// overflows, which faults before anything is written.
+fn materialized(instruction: &Instruction, params: &[isize]) -> Option<isize> {
+    if instruction.modes[..2] != [Mode::Immediate, Mode::Immediate] {
+        return None;
+    }
+    match instruction.opcode {
+        Opcode::Add => params[0].checked_add(params[1]),
+        Opcode::Multiply => params[0].checked_mul(params[1]),
+        _ => None,
+    }
+}
+
+pub fn analyze(program: &[isize]) -> Analysis {
+    let mut lines = BTreeMap::new();
+    let mut edges = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    let mut invalid_targets = BTreeSet::new();
+
+    let mut work = vec![0];
+    while let Some(addr) = work.pop() {
+        if addr >= program.len() || lines.contains_key(&addr) || invalid_targets.contains(&addr) {
+            continue;
+        }
+        let line = Line::decode(program, addr);
+        if let Line::Data { .. } = line {
+            invalid_targets.insert(addr);
+            continue;
+        }
+        if let Line::Instruction { instruction, params, .. } = &line {
+            let out = successors(addr, instruction, params);
+            for edge in &out {
+                match *edge {
+                    Edge::Jump(target) => {
+                        leaders.insert(target);
+                        work.push(target);
+                    },
+                    Edge::Fallthrough(next) => work.push(next),
+                    Edge::Indirect => {},
+                }
+            }
+            if matches!(instruction.opcode, Opcode::JumpIfTrue | Opcode::JumpIfFalse) {
+                leaders.insert(addr + instruction.width());
+            }
+            if let Some(target) = materialized(instruction, params) {
+                if target > 0 && (target as usize) < program.len() {
+                    if let Line::Instruction { .. } = Line::decode(program, target as usize) {
+                        leaders.insert(target as usize);
+                        work.push(target as usize);
+                    }
+                }
+            }
+            edges.insert(addr, out);
+        }
+        lines.insert(addr, line);
+    }
+
+    // Split the reachable instructions into basic blocks.
+    let mut blocks = BTreeMap::new();
+    for &start in leaders.iter().filter(|l| lines.contains_key(l)) {
+        let mut addr = start;
+        let block_edges = loop {
+            let line = &lines[&addr];
+            let next = addr + line.width();
+            let out = edges.get(&addr).cloned().unwrap_or_default();
+            if out != vec![Edge::Fallthrough(next)] || leaders.contains(&next) || !lines.contains_key(&next) {
+                break out;
+            }
+            addr = next;
+        };
+        let end = addr + lines[&addr].width();
+        blocks.insert(start, Block { start, end, edges: block_edges });
+    }
+
+    let mut covered = vec![false; program.len()];
+    for line in lines.values() {
+        let end = std::cmp::min(line.addr() + line.width(), program.len());
+        for c in &mut covered[line.addr()..end] {
+            *c = true;
+        }
+    }
+    let mut unreachable = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        if covered[addr] {
+            addr += 1;
+            continue;
+        }
+        let start = addr;
+        while addr < program.len() && !covered[addr] {
+            addr += 1;
+        }
+        unreachable.push(start..addr);
+    }
+
+    let mut self_modifying = Vec::new();
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut indirect_jumps = Vec::new();
+    for (&addr, line) in &lines {
+        if let Line::Instruction { instruction, params, .. } = line {
+            match instruction.opcode {
+                Opcode::Input => inputs.push(addr),
+                Opcode::Output => outputs.push(addr),
+                _ => {},
+            }
+            if edges[&addr].contains(&Edge::Indirect) {
+                indirect_jumps.push(addr);
+            }
+            if instruction.opcode.writes() {
+                let param = instruction.width() - 2;
+                if instruction.modes[param] == Mode::Position && params[param] >= 0
+                    && covered.get(params[param] as usize) == Some(&true) {
+                    self_modifying.push((addr, params[param] as usize));
+                }
+            }
+        }
+    }
+
+    let invalid_targets = invalid_targets.into_iter().collect();
+    Analysis { lines, blocks, unreachable, self_modifying, inputs, outputs, invalid_targets, indirect_jumps }
+}
+
+impl Analysis {
+
+    /// Whether `addr` is part of an instruction reachable from the start.
+    pub fn is_code(&self, addr: usize) -> bool {
+        self.lines.range(..=addr).next_back()
+            .is_some_and(|(&start, line)| addr < start + line.width())
+    }
+
+    /// The control-flow graph in Graphviz format, one box per basic block.
+    /// Blocks that rewrite code are drawn red and ones that do I/O blue.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks.values() {
+            let contains = |sites: &[usize]| sites.iter().any(|&pc| pc >= block.start && pc < block.end);
+            let modifies = self.self_modifying.iter().any(|&(pc, _)| pc >= block.start && pc < block.end);
+            let color = if modifies {
+                ", color=red"
+            } else if contains(&self.inputs) || contains(&self.outputs) {
+                ", color=blue"
+            } else {
+                ""
+            };
+
+            let label : String = self.lines.range(block.start..block.end)
+                .map(|(_, line)| format!("{}\\l", line))
+                .collect();
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, color).unwrap();
+
+            for edge in &block.edges {
+                match edge {
+                    Edge::Fallthrough(next) => writeln!(dot, "    b{} -> b{};", block.start, next),
+                    Edge::Jump(target) => writeln!(dot, "    b{} -> b{} [label=\"jump\"];", block.start, target),
+                    Edge::Indirect => writeln!(dot, "    b{} -> indirect [style=dashed];", block.start),
+                }.unwrap();
+            }
+        }
+
+        for target in &self.invalid_targets {
+            writeln!(dot, "    b{} [shape=ellipse, label=\"{:04}: invalid\", color=orange];", target, target).unwrap();
+        }
+        if !self.indirect_jumps.is_empty() {
+            writeln!(dot, "    indirect [shape=ellipse, label=\"indirect jump\"];").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn analyze_example() {
+        let program = assemble("
+                    IN -> [x]
+                    JZ [x], #skip
+                    OUT [x]
+            skip:   ADD #7, #0 -> [12]
+                    OUT #0
+                    HLT
+            dead:   OUT #99
+            x:      DATA 0
+        ").unwrap();
+
+        let analysis = analyze(&program);
+        assert_eq!(vec![0, 2, 5, 7, 11, 13], analysis.lines.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(vec![14..17], analysis.unreachable);
+        assert_eq!(vec![(7, 12)], analysis.self_modifying);
+        assert_eq!((vec![0], vec![5, 11]), (analysis.inputs.clone(), analysis.outputs.clone()));
+        assert!(analysis.is_code(12) && !analysis.is_code(14));
+
+        assert_eq!(vec![
+            Block { start: 0, end: 5, edges: vec![Edge::Jump(7), Edge::Fallthrough(5)] },
+            Block { start: 5, end: 7, edges: vec![Edge::Fallthrough(7)] },
+            Block { start: 7, end: 14, edges: vec![] },
+        ], analysis.blocks.values().cloned().collect::<Vec<_>>());
+
+        let dot = analysis.to_dot();
+        assert!(dot.contains("b0 -> b7 [label=\"jump\"];"));
+        assert!(dot.contains("b7 [label=\"0007: ADD #7, #0 -> [12]\\l0011: OUT #0\\l0013: HLT\\l\", color=red];"));
+    }
+
+    #[test]
+    fn indirect_example() {
+        // Calls a function that returns through the stack.
+        let program = assemble("
+                    ARB #100
+                    ADD #ret, #0 -> [rb]
+                    JNZ #1, #func
+            ret:    HLT
+            func:   OUT #1
+                    JZ #0, [rb]
+        ").unwrap();
+
+        let analysis = analyze(&program);
+        assert_eq!(vec![12], analysis.indirect_jumps);
+        assert!(analysis.unreachable.is_empty());
+        assert_eq!(vec![Edge::Jump(10)], analysis.blocks[&0].edges);
+        assert_eq!(vec![Edge::Indirect], analysis.blocks[&10].edges);
+        assert!(analysis.to_dot().contains("b10 -> indirect [style=dashed];"));
+    }
+
+    #[test]
+    fn invalid_target_example() {
+        // Jumps straight into a word that isn't an instruction.
+        let analysis = analyze(&[1105,1,5,99,0,42]);
+        assert_eq!(vec![0], analysis.lines.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(vec![5], analysis.invalid_targets);
+        assert_eq!(vec![3..6], analysis.unreachable);
+        assert!(!analysis.is_code(5));
+        assert!(analysis.to_dot().contains("b5 [shape=ellipse, label=\"0005: invalid\", color=orange];"));
+    }
+
+    #[test]
+    fn overflow_example() {
+        let program = [1101,isize::MAX,1,0, 1102,isize::MIN,-1,0, 99];
+        let analysis = analyze(&program);
+        assert_eq!(vec![0, 4, 8], analysis.lines.keys().cloned().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::{IntCode, Opcode, RunState};
+use super::asm::parse_opcode;
+use super::disasm::Line;
+
+const HELP : &str = "\
+s, step [n]         execute n instructions (default 1)
+c, continue         run until a breakpoint, watchpoint, input request or halt
+b, break <pc>       stop before executing the instruction at pc
+bo <opcode>         stop before any instruction with this opcode (mnemonic or number)
+w, watch <addr>     stop after the value at addr changes
+clear               remove all breakpoints and watchpoints
+i, input <n>...     queue numeric inputs
+a, ascii <text>     queue a line of text as ASCII, followed by a newline
+r, regs             show pc, relative base, pending inputs and the next instruction
+m, mem <addr> [n]   show n words of memory (default 8)
+l, list [addr] [n]  disassemble n instructions from addr (default pc, 10)
+q, quit             exit";
+
+/// Why execution stopped.
+#[derive(Debug, PartialEq, Eq)]
+enum Stop {
+    Breakpoint(usize),
+    OpcodeBreak(Opcode),
+    Watch { addr: usize, old: isize, new: isize },
+    Steps,
+    NeedsInput,
+    Halted,
+    Fault(String),
+}
+
+/// An interactive front end for stepping through an IntCode program. Every
+/// command returns the text that should be shown to the user.
+pub struct Debugger {
+    comp: IntCode,
+    breakpoints: BTreeSet<usize>,
+    opcode_breaks: Vec<Opcode>,
+    watches: BTreeMap<usize, isize>,
+    outputs: Vec<isize>,
+}
+
+fn parse_number(s: Option<&str>) -> Result<usize, String> {
+    let s = s.ok_or_else(|| "missing argument".to_string())?;
+    s.parse().map_err(|_| format!("not a number: '{}'", s))
+}
+
+impl Debugger {
+
+    pub fn new(comp: IntCode) -> Debugger {
+        Debugger {
+            comp,
+            breakpoints: BTreeSet::new(),
+            opcode_breaks: Vec::new(),
+            watches: BTreeMap::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn comp(&self) -> &IntCode {
+        &self.comp
+    }
+
+    /// Every value the program has output so far.
+    pub fn outputs(&self) -> &[isize] {
+        &self.outputs
+    }
+
+    pub fn help() -> &'static str {
+        HELP
+    }
+
+    pub fn execute(&mut self, command: &str) -> Result<String, String> {
+        let mut words = command.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return Ok(String::new()),
+        };
+
+        match name {
+            "s" | "step" => {
+                let count = words.next().map_or(Ok(1), |n| parse_number(Some(n)))?;
+                Ok(self.run(Some(count)))
+            },
+            "c" | "continue" => Ok(self.run(None)),
+            "b" | "break" => {
+                let pc = parse_number(words.next())?;
+                self.breakpoints.insert(pc);
+                Ok(format!("breakpoint at {:04}", pc))
+            },
+            "bo" => {
+                let code = words.next().ok_or_else(|| "missing opcode".to_string())?;
+                let opcode = code.parse().ok().and_then(Opcode::from_code)
+                    .or_else(|| parse_opcode(code))
+                    .ok_or_else(|| format!("unknown opcode '{}'", code))?;
+                self.opcode_breaks.push(opcode);
+                Ok(format!("breakpoint on {}", opcode.mnemonic()))
+            },
+            "w" | "watch" => {
+                let addr = parse_number(words.next())?;
+                self.watches.insert(addr, self.comp.memory().get(addr));
+                Ok(format!("watching [{}] = {}", addr, self.comp.memory().get(addr)))
+            },
+            "clear" => {
+                self.breakpoints.clear();
+                self.opcode_breaks.clear();
+                self.watches.clear();
+                Ok("cleared all breakpoints and watchpoints".to_string())
+            },
+            "i" | "input" => {
+                let inputs = words.map(|w| w.parse().map_err(|_| format!("not a number: '{}'", w)))
+                    .collect::<Result<Vec<isize>, _>>()?;
+                self.comp.push_inputs(&inputs);
+                Ok(format!("{} inputs pending", self.comp.pending_inputs()))
+            },
+            "a" | "ascii" => {
+                let text = command.trim_start()[name.len()..].trim();
+                let inputs : Vec<isize> = text.bytes().chain(Some(b'\n')).map(isize::from).collect();
+                self.comp.push_inputs(&inputs);
+                Ok(format!("{} inputs pending", self.comp.pending_inputs()))
+            },
+            "r" | "regs" => Ok(self.registers()),
+            "m" | "mem" => {
+                let addr = parse_number(words.next())?;
+                let count = words.next().map_or(Ok(8), |n| parse_number(Some(n)))?;
+                let values = self.comp.memory().read(addr..addr+count);
+                let values : Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                Ok(format!("{:04}: {}", addr, values.join(" ")))
+            },
+            "l" | "list" => {
+                let mut addr = words.next().map_or(Ok(self.comp.pc()), |n| parse_number(Some(n)))?;
+                let count = words.next().map_or(Ok(10), |n| parse_number(Some(n)))?;
+                let mut lines = Vec::new();
+                for _ in 0..count {
+                    let line = Line::decode_at(self.comp.memory(), addr);
+                    addr += line.width();
+                    lines.push(line.to_string());
+                }
+                Ok(lines.join("\n"))
+            },
+            "h" | "help" => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command '{}', try 'help'", name)),
+        }
+    }
+
+    fn registers(&self) -> String {
+        format!("pc {:04}  rb {}  inputs {}\n{}",
+            self.comp.pc(),
+            self.comp.relative_base(),
+            self.comp.pending_inputs(),
+            Line::decode_at(self.comp.memory(), self.comp.pc()))
+    }
+
+    fn check_breaks(&self) -> Option<Stop> {
+        let pc = self.comp.pc();
+        if self.breakpoints.contains(&pc) {
+            return Some(Stop::Breakpoint(pc));
+        }
+        if let Line::Instruction { instruction, .. } = Line::decode_at(self.comp.memory(), pc) {
+            if self.opcode_breaks.contains(&instruction.opcode) {
+                return Some(Stop::OpcodeBreak(instruction.opcode));
+            }
+        }
+        None
+    }
+
+    fn check_watches(&mut self) -> Option<Stop> {
+        for (&addr, old) in self.watches.iter_mut() {
+            let new = self.comp.memory().get(addr);
+            if new != *old {
+                let stop = Stop::Watch { addr, old: *old, new };
+                *old = new;
+                return Some(stop);
+            }
+        }
+        None
+    }
+
+    /// Executes instructions until something interesting happens, or until
+    /// `limit` instructions have run.
+    fn run(&mut self, limit: Option<usize>) -> String {
+        let mut text = String::new();
+        let mut executed = 0;
+
+        let stop = loop {
+            if limit == Some(executed) {
+                break Stop::Steps;
+            }
+            // Don't trip over the breakpoint we're stopped on.
+            if executed > 0 {
+                if let Some(stop) = self.check_breaks() {
+                    break stop;
+                }
+            }
+
+            match self.comp.step() {
+                Ok(None) => {},
+                Ok(Some(RunState::Output(value))) => {
+                    self.outputs.push(value);
+                    writeln!(text, "output {}", value).unwrap();
+                },
+                Ok(Some(RunState::NeedsInput)) => break Stop::NeedsInput,
+                Ok(Some(RunState::Halted)) => break Stop::Halted,
+                Err(fault) => break Stop::Fault(fault.to_string()),
+            }
+            executed += 1;
+
+            if let Some(stop) = self.check_watches() {
+                break stop;
+            }
+        };
+
+        match stop {
+            Stop::Breakpoint(pc) => writeln!(text, "breakpoint at {:04}", pc),
+            Stop::OpcodeBreak(opcode) => writeln!(text, "breakpoint on {}", opcode.mnemonic()),
+            Stop::Watch { addr, old, new } => writeln!(text, "watch [{}]: {} -> {}", addr, old, new),
+            Stop::Steps => Ok(()),
+            Stop::NeedsInput => writeln!(text, "waiting for input"),
+            Stop::Halted => writeln!(text, "halted"),
+            Stop::Fault(fault) => writeln!(text, "fault: {}", fault),
+        }.unwrap();
+        text.push_str(&self.registers());
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts down from the input, outputting each value.
+    const COUNTDOWN : &[isize] = &[3,20,4,20,1001,20,-1,20,1005,20,2,99];
+
+    #[test]
+    fn breakpoint_example() {
+        let mut debugger = Debugger::new(IntCode::new(COUNTDOWN));
+        assert_eq!(Ok("waiting for input\npc 0000  rb 0  inputs 0\n0000: IN -> [20]".to_string()), debugger.execute("c"));
+
+        debugger.execute("input 3").unwrap();
+        debugger.execute("break 8").unwrap();
+        let text = debugger.execute("continue").unwrap();
+        assert!(text.starts_with("output 3\nbreakpoint at 0008\n"));
+
+        debugger.execute("clear").unwrap();
+        let text = debugger.execute("c").unwrap();
+        assert!(text.contains("halted"));
+        assert_eq!(&[3, 2, 1], debugger.outputs());
+    }
+
+    #[test]
+    fn watch_example() {
+        let mut debugger = Debugger::new(IntCode::with_inputs(COUNTDOWN, &[2]));
+        debugger.execute("w 20").unwrap();
+        let text = debugger.execute("c").unwrap();
+        assert!(text.starts_with("watch [20]: 0 -> 2\npc 0002"));
+
+        let text = debugger.execute("c").unwrap();
+        assert!(text.starts_with("output 2\nwatch [20]: 2 -> 1\npc 0008"));
+    }
+
+    #[test]
+    fn opcode_break_example() {
+        let mut debugger = Debugger::new(IntCode::with_inputs(COUNTDOWN, &[2]));
+        debugger.execute("bo JNZ").unwrap();
+        let text = debugger.execute("c").unwrap();
+        assert!(text.starts_with("output 2\nbreakpoint on JNZ\npc 0008"));
+
+        let text = debugger.execute("step 2").unwrap();
+        assert_eq!("output 1\npc 0004  rb 0  inputs 0\n0004: ADD [20], #-1 -> [20]", text);
+    }
+}
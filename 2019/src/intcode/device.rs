@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{IntCode, IntCodeFault, RunState};
+
+/// A peripheral wired to a machine's input and output instructions.
+/// Attach one (or a tuple of them) with `IntCode::run_with`.
+pub trait Device {
+    /// The next value for an input instruction, or `None` to make the
+    /// machine give control back to the caller.
+    fn input(&mut self) -> Option<isize> {
+        None
+    }
+
+    fn output(&mut self, _value: isize) {}
+
+    /// Called once at the start of every `run_with`, i.e. every frame of a
+    /// game or round of a network.
+    fn tick(&mut self) {}
+}
+
+/// Two devices on the same machine: inputs come from the first one that has
+/// any, and both see every output.
+impl<A: Device, B: Device> Device for (A, B) {
+    fn input(&mut self) -> Option<isize> {
+        self.0.input().or_else(|| self.1.input())
+    }
+
+    fn output(&mut self, value: isize) {
+        self.0.output(value);
+        self.1.output(value);
+    }
+
+    fn tick(&mut self) {
+        self.0.tick();
+        self.1.tick();
+    }
+}
+
+impl<D: Device + ?Sized> Device for &mut D {
+    fn input(&mut self) -> Option<isize> {
+        (**self).input()
+    }
+
+    fn output(&mut self, value: isize) {
+        (**self).output(value)
+    }
+
+    fn tick(&mut self) {
+        (**self).tick()
+    }
+}
+
+impl IntCode {
+
+    /// Runs with `device` attached until the program halts or the device has
+    /// no input for it, returning `Halted` or `NeedsInput` respectively.
+    /// Inputs already queued on the machine are used before asking the device.
+    pub fn run_with<D: Device + ?Sized>(&mut self, device: &mut D) -> Result<RunState, IntCodeFault> {
+        device.tick();
+        loop {
+            match self.resume()? {
+                RunState::Output(value) => device.output(value),
+                RunState::NeedsInput => match device.input() {
+                    Some(input) => self.push_input(input),
+                    None => return Ok(RunState::NeedsInput),
+                },
+                RunState::Halted => return Ok(RunState::Halted),
+            }
+        }
+    }
+}
+
+/// Hands the machine the moves it's been given, one per input.
+#[derive(Clone, Debug, Default)]
+pub struct Joystick {
+    moves: VecDeque<isize>,
+}
+
+impl Joystick {
+
+    pub fn push(&mut self, position: isize) {
+        self.moves.push_back(position);
+    }
+}
+
+impl Device for Joystick {
+    fn input(&mut self) -> Option<isize> {
+        self.moves.pop_front()
+    }
+}
+
+/// Types text for an ASCII program, one character per input.
+#[derive(Clone, Debug, Default)]
+pub struct Keyboard {
+    keys: VecDeque<isize>,
+}
+
+impl Keyboard {
+
+    pub fn new(text: &str) -> Keyboard {
+        Keyboard { keys: text.bytes().map(isize::from).collect() }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Keyboard> {
+        Ok(Keyboard::new(&fs::read_to_string(path)?))
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl Device for Keyboard {
+    fn input(&mut self) -> Option<isize> {
+        self.keys.pop_front()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub addr: usize,
+    pub x: isize,
+    pub y: isize,
+}
+
+/// A network card that speaks the day 23 protocol. It boots by giving the
+/// machine its address, then on every tick offers one queued packet, or -1
+/// if there are none. Packets the machine sends collect in the outbox.
+#[derive(Clone, Debug)]
+pub struct Nic {
+    address: isize,
+    booted: bool,
+    polled: bool,
+    pending_y: Option<isize>,
+    partial: Vec<isize>,
+    idle: bool,
+    pub inbox: VecDeque<(isize, isize)>,
+    pub outbox: Vec<Packet>,
+}
+
+impl Nic {
+
+    pub fn new(address: usize) -> Nic {
+        Nic {
+            address: address as isize,
+            booted: false,
+            polled: false,
+            pending_y: None,
+            partial: Vec::new(),
+            idle: true,
+            inbox: VecDeque::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Whether the machine neither received nor sent anything since the
+    /// last tick.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+}
+
+impl Device for Nic {
+    fn input(&mut self) -> Option<isize> {
+        if !self.booted {
+            self.booted = true;
+            return Some(self.address);
+        }
+        if let Some(y) = self.pending_y.take() {
+            return Some(y);
+        }
+        if self.polled {
+            return None;
+        }
+        self.polled = true;
+        match self.inbox.pop_front() {
+            Some((x, y)) => {
+                self.idle = false;
+                self.pending_y = Some(y);
+                Some(x)
+            },
+            None => Some(-1),
+        }
+    }
+
+    fn output(&mut self, value: isize) {
+        self.partial.push(value);
+        if let [addr, x, y] = self.partial[..] {
+            self.partial.clear();
+            self.idle = false;
+            self.outbox.push(Packet { addr: addr as usize, x, y });
+        }
+    }
+
+    fn tick(&mut self) {
+        self.polled = false;
+        self.idle = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn devices_example() {
+        // Adds pairs of inputs until it reads a zero.
+        let program = assemble("
+            loop:   IN -> [a]
+                    JZ [a], #done
+                    IN -> [b]
+                    ADD [a], [b] -> [a]
+                    OUT [a]
+                    JNZ #1, #loop
+            done:   HLT
+            a:      DATA 0
+            b:      DATA 0
+        ").unwrap();
+
+        #[derive(Default)]
+        struct Printer(Vec<isize>);
+
+        impl Device for Printer {
+            fn output(&mut self, value: isize) {
+                self.0.push(value);
+            }
+        }
+
+        let mut comp = IntCode::new(&program);
+        let mut devices = (Joystick::default(), Printer::default());
+        devices.0.push(1);
+        devices.0.push(2);
+        devices.0.push(3);
+        assert_eq!(Ok(RunState::NeedsInput), comp.run_with(&mut devices));
+        assert_eq!(vec![3], devices.1.0);
+
+        let mut keyboard = Keyboard::new("\x04\0");
+        assert_eq!(Ok(RunState::Halted), comp.run_with(&mut (&mut keyboard, &mut devices.1)));
+        assert_eq!(vec![3, 7], devices.1.0);
+        assert_eq!(0, keyboard.remaining());
+    }
+
+    #[test]
+    fn nic_example() {
+        // Forwards every packet it receives to address 255, with y doubled.
+        let program = assemble("
+                    IN -> [addr]
+            loop:   IN -> [x]
+                    EQ [x], #-1 -> [empty]
+                    JNZ [empty], #loop
+                    IN -> [y]
+                    MUL [y], #2 -> [y]
+                    OUT #255
+                    OUT [x]
+                    OUT [y]
+                    JNZ #1, #loop
+            addr:   DATA 0
+            x:      DATA 0
+            y:      DATA 0
+            empty:  DATA 0
+        ").unwrap();
+
+        let mut comp = IntCode::new(&program);
+        let mut nic = Nic::new(7);
+        assert_eq!(Ok(RunState::NeedsInput), comp.run_with(&mut nic));
+        assert_eq!(7, comp.peek(program.len() - 4));
+        assert!(nic.is_idle());
+
+        nic.inbox.push_back((3, 4));
+        nic.inbox.push_back((5, 6));
+        assert_eq!(Ok(RunState::NeedsInput), comp.run_with(&mut nic));
+        assert_eq!(vec![Packet { addr: 255, x: 3, y: 8 }], nic.outbox);
+        assert!(!nic.is_idle());
+        assert_eq!(1, nic.inbox.len());
+    }
+}
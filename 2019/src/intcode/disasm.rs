@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::{Instruction, Mode, Opcode};
+use super::memory::Memory;
+use super::word::Word;
+
+impl Opcode {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Multiply => "MUL",
+            Opcode::Input => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JNZ",
+            Opcode::JumpIfFalse => "JZ",
+            Opcode::LessThan => "LT",
+            Opcode::Equals => "EQ",
+            Opcode::AdjustRelativeBase => "ARB",
+            Opcode::Halt => "HLT",
+        }
+    }
+}
+
+/// One line of a listing: either a decodable instruction with its raw
+/// parameters, or a single word that doesn't decode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    Instruction { addr: usize, instruction: Instruction, params: Vec<isize> },
+    Data { addr: usize, value: isize },
+}
+
+impl Line {
+    /// Decodes the word at `addr`, falling back to `Data` if it isn't a
+    /// valid instruction or its parameters run off the end of `memory`.
+    pub fn decode(memory: &[isize], addr: usize) -> Line {
+        Line::from_words(addr, &memory[addr..])
+    }
+
+    /// Decodes the word at `addr` in a running machine's memory. Words too
+    /// wide for an `isize` are shown saturated.
+    pub fn decode_at<W: Word>(memory: &Memory<W>, addr: usize) -> Line {
+        let words : Vec<isize> = memory.read(addr..addr+4).iter().map(W::saturating_to_isize).collect();
+        Line::from_words(addr, &words)
+    }
+
+    /// Decodes `words`, which start at `addr`.
+    fn from_words(addr: usize, words: &[isize]) -> Line {
+        let value = words[0];
+        match Instruction::decode(value) {
+            Ok(instruction) if instruction.width() <= words.len() => {
+                let params = words[1..instruction.width()].to_vec();
+                Line::Instruction { addr, instruction, params }
+            },
+            _ => Line::Data { addr, value },
+        }
+    }
+
+    pub fn addr(&self) -> usize {
+        match self {
+            Line::Instruction { addr, .. } | Line::Data { addr, .. } => *addr,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            Line::Instruction { instruction, .. } => instruction.width(),
+            Line::Data { .. } => 1,
+        }
+    }
+
+    /// Immediate-mode target of a conditional jump, if it has one.
+    pub fn jump_target(&self) -> Option<usize> {
+        match self {
+            Line::Instruction { instruction, params, .. } => {
+                match instruction.opcode {
+                    Opcode::JumpIfTrue | Opcode::JumpIfFalse
+                        if instruction.modes[1] == Mode::Immediate && params[1] >= 0 => {
+                        Some(params[1] as usize)
+                    },
+                    _ => None,
+                }
+            },
+            Line::Data { .. } => None,
+        }
+    }
+
+    /// Formats the line, naming jump targets that appear in `labels`.
+    pub fn format(&self, labels: &BTreeMap<usize, String>) -> String {
+        match self {
+            Line::Data { addr, value } => format!("{:04}: DATA {}", addr, value),
+            Line::Instruction { addr, instruction, params } => {
+                let target = self.jump_target().and_then(|t| labels.get(&t));
+                let mut operands : Vec<String> = params.iter()
+                    .zip(instruction.modes.iter())
+                    .map(|(value, mode)| format_operand(*mode, *value))
+                    .collect();
+                if let Some(target) = target {
+                    operands[1] = format!("#{}", target);
+                }
+
+                let output = if instruction.opcode.writes() { operands.pop() } else { None };
+
+                let mut text = format!("{:04}: {}", addr, instruction.opcode.mnemonic());
+                if !operands.is_empty() {
+                    text += " ";
+                    text += &operands.join(", ");
+                }
+                if let Some(output) = output {
+                    text += " -> ";
+                    text += &output;
+                }
+                text
+            },
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&BTreeMap::new()))
+    }
+}
+
+pub fn format_operand(mode: Mode, value: isize) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate => format!("#{}", value),
+        Mode::Relative if value < 0 => format!("[rb{}]", value),
+        Mode::Relative => format!("[rb+{}]", value),
+    }
+}
+
+pub struct Listing {
+    pub lines: Vec<Line>,
+    pub labels: BTreeMap<usize, String>,
+}
+
+/// Linear sweep over `program`, labelling every jump target that lands on
+/// the start of a line.
+pub fn disassemble(program: &[isize]) -> Listing {
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        let line = Line::decode(program, addr);
+        addr += line.width();
+        lines.push(line);
+    }
+
+    let starts : BTreeSet<usize> = lines.iter().map(|l| l.addr()).collect();
+    let labels = lines.iter()
+        .filter_map(|l| l.jump_target())
+        .filter(|t| starts.contains(t))
+        .map(|t| (t, format!("L{:04}", t)))
+        .collect();
+
+    Listing { lines, labels }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.labels.get(&line.addr()) {
+                writeln!(f, "{}:", label)?;
+            }
+            writeln!(f, "{}", line.format(&self.labels))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_example() {
+        assert_eq!("0000: ADD [rb+3], #5 -> [100]", Line::decode(&[1201,3,5,100], 0).to_string());
+        assert_eq!("0000: ARB #1\n0002: OUT [rb-1]\n0004: HLT\n", disassemble(&[109,1,204,-1,99]).to_string());
+    }
+
+    #[test]
+    fn labels_example() {
+        let listing = disassemble(&[3,3,1105,-1,9,1101,0,0,12,4,12,99,1]);
+        assert_eq!(
+            "0000: IN -> [3]\n\
+             0002: JNZ #-1, #L0009\n\
+             0005: ADD #0, #0 -> [12]\n\
+             L0009:\n\
+             0009: OUT [12]\n\
+             0011: HLT\n\
+             0012: DATA 1\n",
+            listing.to_string());
+    }
+
+    #[test]
+    fn data_example() {
+        let listing = disassemble(&[12345,1105,1,2,11101,-7]);
+        assert_eq!(
+            "0000: DATA 12345\n\
+             0001: JNZ #1, #2\n\
+             0004: DATA 11101\n\
+             0005: DATA -7\n",
+            listing.to_string());
+    }
+}
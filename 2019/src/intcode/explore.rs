@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use aoc_common::canvas::Canvas;
+
+use super::{IntCode, IntCodeFault};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tile {
+    #[default]
+    Unknown,
+    Wall,
+    Open,
+    Target,
+}
+
+/// How to talk to a robot that moves one cell per command and reports what
+/// it bumped into.
+pub trait MovementProtocol {
+    /// The command for each direction the robot can move, with the offset
+    /// it moves by.
+    fn commands(&self) -> &[(isize, (isize, isize))];
+
+    /// What the robot found after a move, or `None` if `output` isn't a
+    /// status code. It only moves if it isn't a wall.
+    fn status(&self, output: isize) -> Option<Tile>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExploreError {
+    Fault(IntCodeFault),
+    /// Moving onto `to` got a reply the protocol doesn't understand.
+    UnknownStatus { to: (isize, isize), output: isize },
+    /// Every reachable cell was mapped without finding a target.
+    NoTarget,
+}
+
+impl fmt::Display for ExploreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExploreError::Fault(fault) => write!(f, "{}", fault),
+            ExploreError::UnknownStatus { to, output } =>
+                write!(f, "unknown status {} after moving to {:?}", output, to),
+            ExploreError::NoTarget => write!(f, "the robot never found a target"),
+        }
+    }
+}
+
+impl std::error::Error for ExploreError {}
+
+impl From<IntCodeFault> for ExploreError {
+    fn from(fault: IntCodeFault) -> ExploreError {
+        ExploreError::Fault(fault)
+    }
+}
+
+/// Everything a robot found, relative to where it started at (0, 0).
+pub struct Map {
+    pub tiles: Canvas<Tile>,
+}
+
+impl Map {
+
+    pub fn get(&self, x: isize, y: isize) -> Tile {
+        self.tiles.get(x, y)
+    }
+
+    pub fn targets(&self) -> Vec<(isize, isize)> {
+        self.tiles.pixels().filter(|&(_, t)| t == Tile::Target).map(|(xy, _)| xy).collect()
+    }
+
+    /// The first target found, for robots that are only looking for one.
+    pub fn target(&self) -> Result<(isize, isize), ExploreError> {
+        self.targets().first().cloned().ok_or(ExploreError::NoTarget)
+    }
+
+    /// The number of steps to every reachable cell from `from`.
+    pub fn distances(&self, from: (isize, isize)) -> BTreeMap<(isize, isize), usize> {
+        let mut distances = BTreeMap::new();
+        distances.insert(from, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+
+        while let Some((x, y)) = queue.pop_front() {
+            let distance = distances[&(x, y)];
+            for &(dx, dy) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+                let next = (x + dx, y + dy);
+                match self.get(next.0, next.1) {
+                    Tile::Open | Tile::Target if !distances.contains_key(&next) => {
+                        distances.insert(next, distance + 1);
+                        queue.push_back(next);
+                    },
+                    _ => {},
+                }
+            }
+        }
+        distances
+    }
+
+    pub fn render(&self) -> String {
+        self.tiles.render(|tile| match tile {
+            Tile::Unknown => ' ',
+            Tile::Wall => '#',
+            Tile::Open => '.',
+            Tile::Target => 'O',
+        })
+    }
+}
+
+/// Maps every cell the robot can reach. Instead of walking the robot back
+/// after each probe, it's forked for every move, so each open cell keeps
+/// the robot that reached it.
+pub fn explore<P: MovementProtocol>(protocol: &P, robot: IntCode) -> Result<Map, ExploreError> {
+    let mut tiles = Canvas::new();
+    tiles.set(0, 0, Tile::Open);
+    let mut queue = VecDeque::new();
+    queue.push_back(((0, 0), robot));
+
+    while let Some(((x, y), robot)) = queue.pop_front() {
+        for &(command, (dx, dy)) in protocol.commands() {
+            let (nx, ny) = (x + dx, y + dy);
+            if tiles.get(nx, ny) != Tile::Unknown {
+                continue;
+            }
+
+            let mut robot = robot.clone();
+            robot.push_input(command);
+            let output = robot.expect_output()?;
+            let tile = protocol.status(output)
+                .ok_or(ExploreError::UnknownStatus { to: (nx, ny), output })?;
+            tiles.set(nx, ny, tile);
+            if tile != Tile::Wall {
+                queue.push_back(((nx, ny), robot));
+            }
+        }
+    }
+
+    Ok(Map { tiles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::FaultKind;
+    use crate::intcode::asm::assemble;
+
+    struct Corridor;
+
+    impl MovementProtocol for Corridor {
+        fn commands(&self) -> &[(isize, (isize, isize))] {
+            &[(1, (0, -1)), (2, (0, 1)), (3, (-1, 0)), (4, (1, 0))]
+        }
+
+        fn status(&self, output: isize) -> Option<Tile> {
+            match output {
+                0 => Some(Tile::Wall),
+                1 => Some(Tile::Open),
+                2 => Some(Tile::Target),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn corridor_example() {
+        // Can only go east, and finds the target three steps along.
+        let program = assemble("
+            loop:   IN -> [command]
+                    EQ [command], #4 -> [flag]
+                    JZ [flag], #wall
+                    EQ [x], #3 -> [flag]
+                    JNZ [flag], #wall
+                    ADD [x], #1 -> [x]
+                    EQ [x], #3 -> [flag]
+                    JNZ [flag], #target
+                    OUT #1
+                    JZ #0, #loop
+            target: OUT #2
+                    JZ #0, #loop
+            wall:   OUT #0
+                    JZ #0, #loop
+            command: DATA 0
+            flag:   DATA 0
+            x:      DATA 0
+        ").unwrap();
+
+        let map = explore(&Corridor, IntCode::new(&program)).unwrap();
+        assert_eq!(vec![(3, 0)], map.targets());
+        assert_eq!(Some(&3), map.distances((0, 0)).get(&(3, 0)));
+        assert_eq!(" #### \n#...O#\n #### \n", map.render());
+
+        // Replies 7 to the first move.
+        let error = explore(&Corridor, IntCode::new(&[3,0,104,7,99])).err();
+        assert_eq!(Some(ExploreError::UnknownStatus { to: (0, -1), output: 7 }), error);
+
+        // Halts instead of replying.
+        match explore(&Corridor, IntCode::new(&[3,0,99])).err() {
+            Some(ExploreError::Fault(fault)) => assert_eq!(FaultKind::NoOutput, fault.kind),
+            other => panic!("expected a fault, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use fnv::FnvHashMap;
+
+use super::word::Word;
+
+const PAGE_BITS : usize = 10;
+const PAGE_SIZE : usize = 1 << PAGE_BITS;
+const PAGE_MASK : usize = PAGE_SIZE - 1;
+
+/// Pages below this index live in a `Vec`; anything further out goes in a
+/// hash map so that one stray write to a huge address stays cheap.
+const DENSE_PAGES : usize = 1 << 16;
+
+type Page<W> = Arc<[W; PAGE_SIZE]>;
+
+/// IntCode memory that only allocates the pages a program actually writes.
+/// Every address that has never been written reads as zero.
+/// Clones share pages, copying each one the first time either side writes to it.
+#[derive(Clone, Default)]
+pub struct Memory<W: Word = isize> {
+    pages: Vec<Option<Page<W>>>,
+    far_pages: FnvHashMap<usize, Page<W>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub pages: usize,
+    pub shared_pages: usize,
+    pub bytes: usize,
+}
+
+fn blank_page<W: Word>() -> Page<W> {
+    Arc::new(std::array::from_fn(|_| W::default()))
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(initial: &[W]) -> Memory<W> {
+        let mut memory = Memory::default();
+        for (page, words) in initial.chunks(PAGE_SIZE).enumerate() {
+            memory.page_mut(page)[..words.len()].clone_from_slice(words);
+        }
+        memory
+    }
+
+    pub fn get(&self, addr: usize) -> W {
+        let page = addr >> PAGE_BITS;
+        let page = if page < DENSE_PAGES {
+            self.pages.get(page).and_then(|p| p.as_ref())
+        } else {
+            self.far_pages.get(&page)
+        };
+        page.map_or_else(W::default, |p| p[addr & PAGE_MASK].clone())
+    }
+
+    pub fn get_mut(&mut self, addr: usize) -> &mut W {
+        &mut self.page_mut(addr >> PAGE_BITS)[addr & PAGE_MASK]
+    }
+
+    pub fn read(&self, range: Range<usize>) -> Vec<W> {
+        range.map(|addr| self.get(addr)).collect()
+    }
+
+    /// One past the last address covered by the dense pages.
+    pub fn extent(&self) -> usize {
+        self.pages.len() << PAGE_BITS
+    }
+
+    /// The address ranges of every allocated page, in order. Everything
+    /// outside them has never been written.
+    pub fn allocated(&self) -> Vec<Range<usize>> {
+        let mut pages : Vec<usize> = self.pages.iter().enumerate()
+            .filter(|(_, page)| page.is_some())
+            .map(|(page, _)| page)
+            .chain(self.far_pages.keys().cloned())
+            .collect();
+        pages.sort_unstable();
+        pages.into_iter().map(|page| page << PAGE_BITS..(page + 1) << PAGE_BITS).collect()
+    }
+
+    fn page_mut(&mut self, page: usize) -> &mut [W; PAGE_SIZE] {
+        if page < DENSE_PAGES {
+            if page >= self.pages.len() {
+                self.pages.resize_with(page + 1, || None);
+            }
+            Arc::make_mut(self.pages[page].get_or_insert_with(blank_page))
+        } else {
+            Arc::make_mut(self.far_pages.entry(page).or_insert_with(blank_page))
+        }
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        let pages : Vec<&Page<W>> = self.pages.iter().flatten().chain(self.far_pages.values()).collect();
+        MemoryUsage {
+            pages: pages.len(),
+            shared_pages: pages.iter().filter(|p| Arc::strong_count(p) > 1).count(),
+            bytes: pages.len() * std::mem::size_of::<[W; PAGE_SIZE]>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_on_demand() {
+        let mut memory : Memory = Memory::new(&[1, 2, 3]);
+        assert_eq!(vec![1, 2, 3, 0], memory.read(0..4));
+        assert_eq!(1, memory.usage().pages);
+
+        assert_eq!(0, memory.get(5 * PAGE_SIZE));
+        assert_eq!(1, memory.usage().pages);
+
+        *memory.get_mut(5 * PAGE_SIZE + 1) = 7;
+        *memory.get_mut(1 << 50) = 8;
+        assert_eq!(7, memory.get(5 * PAGE_SIZE + 1));
+        assert_eq!(8, memory.get(1 << 50));
+        assert_eq!(3, memory.usage().pages);
+        assert_eq!(vec![0..PAGE_SIZE, 5 * PAGE_SIZE..6 * PAGE_SIZE, 1 << 50..(1 << 50) + PAGE_SIZE], memory.allocated());
+        assert_eq!(3 * PAGE_SIZE * std::mem::size_of::<isize>(), memory.usage().bytes);
+    }
+
+    #[test]
+    fn copy_on_write() {
+        let mut memory : Memory = Memory::new(&[1; PAGE_SIZE * 2]);
+        let mut fork = memory.clone();
+        assert_eq!(2, memory.usage().shared_pages);
+
+        *fork.get_mut(0) = 5;
+        assert_eq!(1, memory.usage().shared_pages);
+        assert_eq!((1, 5), (memory.get(0), fork.get(0)));
+
+        *memory.get_mut(PAGE_SIZE) = 6;
+        assert_eq!(0, fork.usage().shared_pages);
+        assert_eq!((6, 1), (memory.get(PAGE_SIZE), fork.get(PAGE_SIZE)));
+    }
+}
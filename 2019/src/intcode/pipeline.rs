@@ -0,0 +1,129 @@
+use std::fmt;
+
+use super::{IntCode, IntCodeFault, RunState};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineError {
+    Fault { node: NodeId, fault: IntCodeFault },
+    /// Every node that hasn't halted is waiting for input nobody will send.
+    Deadlock(Vec<NodeId>),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Fault { node, fault } => write!(f, "node {}: {}", node.0, fault),
+            PipelineError::Deadlock(nodes) => {
+                let nodes : Vec<String> = nodes.iter().map(|n| n.0.to_string()).collect();
+                write!(f, "deadlock: nodes {} are all waiting for input", nodes.join(", "))
+            },
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// A graph of machines where each edge feeds one machine's outputs to
+/// another's inputs. Cycles are allowed, so feedback loops run until every
+/// machine halts.
+#[derive(Clone, Default)]
+pub struct Topology {
+    nodes: Vec<IntCode>,
+    edges: Vec<(NodeId, NodeId)>,
+}
+
+impl Topology {
+
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    /// Adds a machine running `program` that starts with `inputs` queued.
+    pub fn node(&mut self, program: &[isize], inputs: &[isize]) -> NodeId {
+        self.nodes.push(IntCode::with_inputs(program, inputs));
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Sends every output of `from` to the input of `to`. A node with several
+    /// outgoing edges sends each output down all of them.
+    pub fn edge(&mut self, from: NodeId, to: NodeId) {
+        self.edges.push((from, to));
+    }
+
+    /// Runs the machines round-robin on this thread until they have all
+    /// halted, and returns everything each one output, indexed by `NodeId`.
+    pub fn run(&self) -> Result<Vec<Vec<isize>>, PipelineError> {
+        let mut nodes = self.nodes.clone();
+        let mut outputs = vec![Vec::new(); nodes.len()];
+        let mut halted = vec![false; nodes.len()];
+
+        while halted.iter().any(|h| !h) {
+            let mut progress = false;
+            for n in 0..nodes.len() {
+                if halted[n] {
+                    continue;
+                }
+                loop {
+                    let state = nodes[n].resume()
+                        .map_err(|fault| PipelineError::Fault { node: NodeId(n), fault })?;
+                    match state {
+                        RunState::Output(value) => {
+                            progress = true;
+                            outputs[n].push(value);
+                            for &(_, to) in self.edges.iter().filter(|(from, _)| from.0 == n) {
+                                nodes[to.0].push_input(value);
+                            }
+                        },
+                        RunState::NeedsInput => break,
+                        RunState::Halted => {
+                            progress = true;
+                            halted[n] = true;
+                            break;
+                        },
+                    }
+                }
+            }
+
+            if !progress {
+                let waiting = (0..nodes.len()).filter(|&n| !halted[n]).map(NodeId).collect();
+                return Err(PipelineError::Deadlock(waiting));
+            }
+        }
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a value and outputs it plus one.
+    const INCREMENT : &[isize] = &[3,9,1001,9,1,9,4,9,99,0];
+
+    #[test]
+    fn chain_example() {
+        let mut topology = Topology::new();
+        let a = topology.node(INCREMENT, &[1]);
+        let b = topology.node(INCREMENT, &[]);
+        let c = topology.node(INCREMENT, &[]);
+        topology.edge(a, b);
+        topology.edge(b, c);
+        topology.edge(a, c);
+        // c only reads one value, and a's output reaches it before b's.
+        assert_eq!(Ok(vec![vec![2], vec![3], vec![3]]), topology.run());
+    }
+
+    #[test]
+    fn deadlock_example() {
+        let mut topology = Topology::new();
+        let a = topology.node(INCREMENT, &[]);
+        let b = topology.node(INCREMENT, &[]);
+        topology.edge(a, b);
+        topology.edge(b, a);
+        assert_eq!(Err(PipelineError::Deadlock(vec![a, b])), topology.run());
+    }
+}
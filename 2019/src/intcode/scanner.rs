@@ -0,0 +1,111 @@
+use super::IntCode;
+use super::memory::Memory;
+
+/// Narrows down where a running program keeps a value, game-genie style:
+/// snapshot memory, let the program run a little, then keep only the cells
+/// that changed, stayed the same or hold a known value. Repeat until one
+/// address is left, then `peek` or `poke` it between resumes.
+pub struct Scanner {
+    snapshot: Memory,
+    candidates: Vec<usize>,
+}
+
+impl Scanner {
+
+    /// Starts with every address in a page the program has loaded or
+    /// written as a candidate. The rest of memory has only ever held zero.
+    pub fn new(comp: &IntCode) -> Scanner {
+        let snapshot = comp.memory().clone();
+        let candidates = snapshot.allocated().into_iter().flatten().collect();
+        Scanner { snapshot, candidates }
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// The value each candidate held when the last snapshot was taken.
+    pub fn snapshot(&self) -> Vec<(usize, isize)> {
+        self.candidates.iter().map(|&addr| (addr, self.snapshot.get(addr))).collect()
+    }
+
+    /// Keeps the candidates for which `keep(old, new)` holds, comparing the
+    /// last snapshot with memory now, and takes a new snapshot. Returns how
+    /// many candidates are left.
+    pub fn retain<F: FnMut(isize, isize) -> bool>(&mut self, comp: &IntCode, mut keep: F) -> usize {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&addr| keep(snapshot.get(addr), comp.peek(addr)));
+        self.snapshot = comp.memory().clone();
+        self.candidates.len()
+    }
+
+    pub fn changed(&mut self, comp: &IntCode) -> usize {
+        self.retain(comp, |old, new| old != new)
+    }
+
+    pub fn unchanged(&mut self, comp: &IntCode) -> usize {
+        self.retain(comp, |old, new| old == new)
+    }
+
+    pub fn equal(&mut self, comp: &IntCode, value: isize) -> usize {
+        self.retain(comp, |_, new| new == value)
+    }
+
+    /// The only candidate left, if the scan has narrowed down that far.
+    pub fn found(&self) -> Option<usize> {
+        match self.candidates.as_slice() {
+            &[addr] => Some(addr),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::RunState;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn scan_example() {
+        // Outputs the counter, then adds each input to it.
+        let program = assemble("
+            loop:   OUT [counter]
+                    IN -> [step]
+                    ADD [counter], [step] -> [counter]
+                    JNZ #1, #loop
+            counter: DATA 10
+            step:    DATA 0
+        ").unwrap();
+        let mut comp = IntCode::new(&program);
+        assert_eq!(Ok(RunState::Output(10)), comp.resume());
+
+        let mut scanner = Scanner::new(&comp);
+        assert_eq!(1024, scanner.candidates().len());
+        assert_eq!(1, scanner.equal(&comp, 10));
+        assert_eq!(Some(11), scanner.found());
+
+        let mut scanner = Scanner::new(&comp);
+        comp.push_input(0);
+        assert_eq!(Ok(RunState::Output(10)), comp.resume());
+        scanner.unchanged(&comp);
+        comp.push_input(5);
+        assert_eq!(Ok(RunState::Output(15)), comp.resume());
+        assert_eq!(2, scanner.changed(&comp));
+        assert_eq!(vec![(11, 15), (12, 5)], scanner.snapshot());
+
+        comp.push_input(1);
+        assert_eq!(Ok(RunState::Output(16)), comp.resume());
+        assert_eq!(1, scanner.retain(&comp, |old, new| new == old + 1));
+        comp.poke(scanner.found().unwrap(), 100);
+        comp.push_input(1);
+        assert_eq!(Ok(RunState::Output(101)), comp.resume());
+
+        // Pages written far beyond the program are scanned too.
+        comp.poke(1 << 40, 101);
+        let mut scanner = Scanner::new(&comp);
+        assert_eq!(2048, scanner.candidates().len());
+        assert_eq!(2, scanner.equal(&comp, 101));
+        assert_eq!(&[11, 1 << 40], scanner.candidates());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::io::{stdout, Write};
+
+use aoc_common::canvas::{Bounds, Canvas};
+use crossterm::{ExecutableCommand, QueueableCommand, cursor, terminal};
+
+use super::device::Device;
+
+/// A display for programs that draw by outputting `(x, y, tile)` triples,
+/// with `(-1, 0, score)` setting the score instead of a tile. Tiles are shown
+/// with the character at their index in the palette.
+///
+/// A live screen also redraws each tile in the terminal as it changes; a
+/// headless one only keeps the state, for tests and batch runs.
+pub struct Screen {
+    palette: Vec<char>,
+    live: bool,
+    tiles: Canvas<isize>,
+    last_drawn: BTreeMap<isize, (isize, isize)>,
+    score: Option<isize>,
+    pending: Vec<isize>,
+}
+
+impl Screen {
+
+    pub fn headless(palette: &str) -> Screen {
+        Screen {
+            palette: palette.chars().collect(),
+            live: false,
+            tiles: Canvas::new(),
+            last_drawn: BTreeMap::new(),
+            score: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Clears the terminal and draws into it from the top left corner.
+    pub fn live(palette: &str) -> Screen {
+        stdout().execute(terminal::Clear(terminal::ClearType::All)).unwrap();
+        Screen { live: true, ..Screen::headless(palette) }
+    }
+
+    /// Takes one output value, acting on it once a whole triple has arrived.
+    pub fn write(&mut self, value: isize) {
+        self.pending.push(value);
+        if let [x, y, tile] = self.pending[..] {
+            self.pending.clear();
+            if (x, y) == (-1, 0) {
+                self.set_score(tile);
+            } else {
+                self.set(x, y, tile);
+            }
+        }
+    }
+
+    pub fn set(&mut self, x: isize, y: isize, tile: isize) {
+        self.tiles.set(x, y, tile);
+        self.last_drawn.insert(tile, (x, y));
+        if self.live && x >= 0 && y >= 0 {
+            self.draw(x as u16, y as u16, &self.glyph(tile).to_string());
+        }
+    }
+
+    fn set_score(&mut self, score: isize) {
+        self.score = Some(score);
+        if self.live {
+            let row = self.bounds().map_or(0, |b| b.max_y + 2);
+            self.draw(0, row.max(0) as u16, &format!("Score: {}", score));
+        }
+    }
+
+    fn draw(&self, x: u16, y: u16, text: &str) {
+        let mut stdout = stdout();
+        stdout
+            .queue(cursor::SavePosition).unwrap()
+            .queue(cursor::MoveTo(x, y)).unwrap();
+        print!("{}", text);
+        stdout.queue(cursor::RestorePosition).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    pub fn get(&self, x: isize, y: isize) -> isize {
+        self.tiles.get(x, y)
+    }
+
+    pub fn tiles(&self) -> &Canvas<isize> {
+        &self.tiles
+    }
+
+    /// Where `tile` was most recently drawn, which for a sprite like a ball
+    /// is where it is now.
+    pub fn last_drawn(&self, tile: isize) -> Option<(isize, isize)> {
+        self.last_drawn.get(&tile).cloned()
+    }
+
+    pub fn count(&self, tile: isize) -> usize {
+        self.tiles.pixels().filter(|&(_, t)| t == tile).count()
+    }
+
+    pub fn score(&self) -> Option<isize> {
+        self.score
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.tiles.bounds()
+    }
+
+    fn glyph(&self, tile: isize) -> char {
+        if tile < 0 {
+            return '?';
+        }
+        *self.palette.get(tile as usize).unwrap_or(&'?')
+    }
+
+    /// The whole screen as text, one line per row.
+    pub fn render(&self) -> String {
+        self.tiles.render(|tile| self.glyph(tile))
+    }
+}
+
+impl Device for Screen {
+    fn output(&mut self, value: isize) {
+        self.write(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_example() {
+        let mut screen = Screen::headless(" #o");
+        for &value in &[0,0,1, 2,0,1, 1,1,2, -1,0,42, 1,1] {
+            screen.write(value);
+        }
+        assert_eq!(Some(42), screen.score());
+        assert_eq!(Some((1, 1)), screen.last_drawn(2));
+        assert_eq!(2, screen.count(1));
+        assert_eq!("# #\n o \n", screen.render());
+
+        // Finishing the last triple erases the o.
+        screen.write(0);
+        assert_eq!(Some((1, 1)), screen.last_drawn(0));
+        assert_eq!("# #\n   \n", screen.render());
+    }
+}
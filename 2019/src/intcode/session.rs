@@ -0,0 +1,267 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use super::{IntCode, IntCodeFault, RunState};
+use super::word::Word;
+
+/// A value that crossed the boundary between a program and the outside.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event<W = isize> {
+    Input(W),
+    Output(W),
+}
+
+impl<W: Word> fmt::Display for Event<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Input(value) => write!(f, "in {}", value),
+            Event::Output(value) => write!(f, "out {}", value),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError<W = isize> {
+    Fault(IntCodeFault),
+    /// Event `index` of the session was `expected` (or the end of it), but
+    /// the program did something else.
+    Diverged { index: usize, expected: Option<Event<W>>, found: RunState<W> },
+    /// The program stopped with this many recorded inputs still unread.
+    Unconsumed { inputs: usize },
+    /// The program's last output wasn't the session's.
+    WrongResult { expected: Option<W>, found: Option<W> },
+}
+
+impl<W: Word> fmt::Display for ReplayError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Fault(fault) => write!(f, "{}", fault),
+            ReplayError::Diverged { index, expected, found } => {
+                write!(f, "diverged at event {}: expected ", index)?;
+                match expected {
+                    Some(event) => write!(f, "{}", event)?,
+                    None => write!(f, "the end of the session")?,
+                }
+                match found {
+                    RunState::Halted => write!(f, " but the program halted"),
+                    RunState::NeedsInput => write!(f, " but the program asked for input"),
+                    RunState::Output(value) => write!(f, " but the program output {}", value),
+                }
+            },
+            ReplayError::Unconsumed { inputs } => write!(f, "the program stopped with {} inputs unread", inputs),
+            ReplayError::WrongResult { expected, found } => {
+                match expected {
+                    Some(value) => write!(f, "expected a last output of {}", value)?,
+                    None => write!(f, "expected no output")?,
+                }
+                match found {
+                    Some(value) => write!(f, " but the program's was {}", value),
+                    None => write!(f, " but the program output nothing"),
+                }
+            },
+        }
+    }
+}
+
+impl<W: Word> std::error::Error for ReplayError<W> {}
+
+impl<W> From<IntCodeFault> for ReplayError<W> {
+    fn from(fault: IntCodeFault) -> ReplayError<W> {
+        ReplayError::Fault(fault)
+    }
+}
+
+/// Every input a program consumed and every output it produced, in order.
+/// Attach one with `IntCode::set_recorder` to capture an interactive run,
+/// save it, and later check that the program still behaves the same with
+/// `replay`.
+///
+/// The file format is one event per line, `in 5` or `out 7`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session<W = isize> {
+    pub events: Vec<Event<W>>,
+}
+
+impl<W> Default for Session<W> {
+    fn default() -> Session<W> {
+        Session { events: Vec::new() }
+    }
+}
+
+impl<W: Word> Session<W> {
+
+    pub fn new() -> Session<W> {
+        Session::default()
+    }
+
+    /// Wraps the session so it can be shared by a machine and its forks.
+    pub fn shared(self) -> Arc<Mutex<Session<W>>> {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Session<W>> {
+        fs::read_to_string(path)?.parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn inputs(&self) -> Vec<W> {
+        self.events.iter().filter_map(|e| match e { Event::Input(v) => Some(v.clone()), _ => None }).collect()
+    }
+
+    pub fn outputs(&self) -> Vec<W> {
+        self.events.iter().filter_map(|e| match e { Event::Output(v) => Some(v.clone()), _ => None }).collect()
+    }
+
+    /// Keeps every input but only the final output, which is usually the
+    /// answer. Far smaller to save when the program draws as it goes.
+    pub fn trimmed(&self) -> Session<W> {
+        let last = self.events.iter().rposition(|e| matches!(e, Event::Output(_)));
+        let events = self.events.iter()
+            .enumerate()
+            .filter(|&(i, e)| matches!(e, Event::Input(_)) || Some(i) == last)
+            .map(|(_, e)| e.clone())
+            .collect();
+        Session { events }
+    }
+
+    pub(super) fn record(&mut self, event: Event<W>) {
+        self.events.push(event);
+    }
+
+    /// Runs `comp`, feeding it the recorded inputs whenever it asks for one
+    /// and checking every output against the recording. Stops with an error
+    /// at the first difference. A session that ends while the program waits
+    /// for more input is fine, since recording may have stopped there too.
+    pub fn replay(&self, mut comp: IntCode<W>) -> Result<(), ReplayError<W>> {
+        for (index, expected) in self.events.iter().enumerate() {
+            match (comp.resume()?, expected) {
+                (RunState::NeedsInput, Event::Input(input)) => comp.push_input(input.clone()),
+                (RunState::Output(actual), Event::Output(value)) if actual == *value => {},
+                (found, _) => {
+                    return Err(ReplayError::Diverged { index, expected: Some(expected.clone()), found });
+                },
+            }
+        }
+        match comp.resume()? {
+            RunState::Halted | RunState::NeedsInput => Ok(()),
+            found => Err(ReplayError::Diverged { index: self.events.len(), expected: None, found }),
+        }
+    }
+}
+
+impl<W: Word> Session<W> {
+
+    /// Feeds `comp` every recorded input and checks only that they're all
+    /// read and that its last output is the session's, so a `trimmed`
+    /// session still pins down the answer.
+    pub fn replay_result(&self, mut comp: IntCode<W>) -> Result<(), ReplayError<W>> {
+        comp.push_inputs(&self.inputs());
+        let mut found = None;
+        for output in comp.outputs() {
+            found = Some(output?);
+        }
+        if comp.pending_inputs() > 0 {
+            return Err(ReplayError::Unconsumed { inputs: comp.pending_inputs() });
+        }
+        let expected = self.outputs().pop();
+        if found != expected {
+            return Err(ReplayError::WrongResult { expected, found });
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> fmt::Display for Session<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> FromStr for Session<W> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Session<W>, String> {
+        let events = s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let mut parts = line.split_whitespace();
+                let value = parts.next_back().and_then(|v| v.parse().ok());
+                match (parts.next(), value) {
+                    (Some("in"), Some(value)) => Ok(Event::Input(value)),
+                    (Some("out"), Some(value)) => Ok(Event::Output(value)),
+                    _ => Err(format!("line {}: expected 'in <value>' or 'out <value>' but found '{}'", i + 1, line)),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Session { events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn replay_example() {
+        // Doubles each input until it's given a zero.
+        let program = assemble("
+            loop:   IN -> [value]
+                    JZ [value], #done
+                    MUL [value], #2 -> [value]
+                    OUT [value]
+                    JNZ #1, #loop
+            done:   HLT
+            value:  DATA 0
+        ").unwrap();
+
+        let session = Session::new().shared();
+        let mut comp = IntCode::with_inputs(&program, &[3, 5]);
+        comp.set_recorder(session.clone());
+        assert_eq!(Ok(vec![6, 10]), comp.outputs().collect());
+
+        let session = session.lock().unwrap().clone();
+        assert_eq!("in 3\nout 6\nin 5\nout 10\n", session.to_string());
+        assert_eq!(Ok(session.clone()), session.to_string().parse());
+        assert_eq!(vec![3, 5], session.inputs());
+        assert!(session.replay(IntCode::new(&program)).is_ok());
+
+        // Tripling instead of doubling diverges at the first output.
+        let mut tripler = program.clone();
+        tripler[7] = 3;
+        let error = session.replay(IntCode::new(&tripler)).unwrap_err();
+        assert_eq!("diverged at event 1: expected out 6 but the program output 9", error.to_string());
+
+        let mut finished = session.clone();
+        finished.events.push(Event::Input(0));
+        assert!(finished.replay(IntCode::new(&program)).is_ok());
+        finished.events.push(Event::Output(0));
+        let error = finished.replay(IntCode::new(&program)).unwrap_err();
+        assert_eq!("diverged at event 5: expected out 0 but the program halted", error.to_string());
+
+        let trimmed = session.trimmed();
+        assert_eq!("in 3\nin 5\nout 10\n", trimmed.to_string());
+        assert!(trimmed.replay_result(IntCode::new(&program)).is_ok());
+        let error = trimmed.replay_result(IntCode::new(&tripler)).unwrap_err();
+        assert_eq!("expected a last output of 10 but the program's was 15", error.to_string());
+        let mut stopped = trimmed.clone();
+        stopped.events.insert(1, Event::Input(0));
+        let error = stopped.replay_result(IntCode::new(&program)).unwrap_err();
+        assert_eq!("the program stopped with 1 inputs unread", error.to_string());
+
+        assert_eq!(Err("line 2: expected 'in <value>' or 'out <value>' but found 'put 3'".to_string()),
+            "in 1\nput 3".parse::<Session>());
+    }
+}
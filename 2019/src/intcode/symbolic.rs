@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{offset_address, FaultKind, Instruction, Mode, Opcode};
+
+/// A polynomial with integer coefficients over named variables. Each term is
+/// keyed by its sorted list of variables, so `3*a*a*b` is `["a","a","b"] => 3`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Expr {
+    terms: BTreeMap<Vec<String>, isize>,
+}
+
+impl Expr {
+
+    pub fn constant(value: isize) -> Expr {
+        let mut expr = Expr::default();
+        if value != 0 {
+            expr.terms.insert(Vec::new(), value);
+        }
+        expr
+    }
+
+    pub fn var(name: &str) -> Expr {
+        let mut expr = Expr::default();
+        expr.terms.insert(vec![name.to_string()], 1);
+        expr
+    }
+
+    pub fn as_constant(&self) -> Option<isize> {
+        match self.terms.len() {
+            0 => Some(0),
+            1 => self.terms.get(&Vec::new()).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.terms.keys().map(|vars| vars.len()).max().unwrap_or(0)
+    }
+
+    /// Replaces `name` with `value` everywhere, or `None` on overflow.
+    pub fn substitute(&self, name: &str, value: isize) -> Option<Expr> {
+        let mut expr = Expr::default();
+        for (vars, &coefficient) in &self.terms {
+            let kept : Vec<String> = vars.iter().filter(|v| *v != name).cloned().collect();
+            let power = (vars.len() - kept.len()) as u32;
+            expr.add_term(kept, value.checked_pow(power)?.checked_mul(coefficient)?)?;
+        }
+        Some(expr)
+    }
+
+    /// If the expression is `a*name + b` for constants `a` and `b`, returns `(a, b)`.
+    pub fn linear_in(&self, name: &str) -> Option<(isize, isize)> {
+        let mut a = 0;
+        let mut b = 0;
+        for (vars, &coefficient) in &self.terms {
+            match &vars[..] {
+                [] => b = coefficient,
+                [v] if v == name => a = coefficient,
+                _ => return None,
+            }
+        }
+        Some((a, b))
+    }
+
+    fn add_term(&mut self, vars: Vec<String>, coefficient: isize) -> Option<()> {
+        let total = self.terms.get(&vars).unwrap_or(&0).checked_add(coefficient)?;
+        if total == 0 {
+            self.terms.remove(&vars);
+        } else {
+            self.terms.insert(vars, total);
+        }
+        Some(())
+    }
+
+    /// The sum, or `None` if a coefficient overflows.
+    pub fn checked_add(&self, other: &Expr) -> Option<Expr> {
+        let mut expr = self.clone();
+        for (vars, &coefficient) in &other.terms {
+            expr.add_term(vars.clone(), coefficient)?;
+        }
+        Some(expr)
+    }
+
+    /// The product, or `None` if a coefficient overflows.
+    pub fn checked_mul(&self, other: &Expr) -> Option<Expr> {
+        let mut expr = Expr::default();
+        for (left, &a) in &self.terms {
+            for (right, &b) in &other.terms {
+                let mut vars : Vec<String> = left.iter().chain(right).cloned().collect();
+                vars.sort();
+                expr.add_term(vars, a.checked_mul(b)?)?;
+            }
+        }
+        Some(expr)
+    }
+}
+
+/// Highest degree first, e.g. `2*a*b + 3*a + 7`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let mut terms : Vec<(&Vec<String>, &isize)> = self.terms.iter().collect();
+        terms.sort_by_key(|(vars, _)| std::cmp::Reverse(vars.len()));
+
+        for (i, (vars, &coefficient)) in terms.into_iter().enumerate() {
+            let magnitude = if i == 0 {
+                if coefficient < 0 { write!(f, "-")?; }
+                coefficient.abs()
+            } else {
+                write!(f, " {} ", if coefficient < 0 { '-' } else { '+' })?;
+                coefficient.abs()
+            };
+            if vars.is_empty() {
+                write!(f, "{}", magnitude)?;
+            } else {
+                if magnitude != 1 {
+                    write!(f, "{}*", magnitude)?;
+                }
+                write!(f, "{}", vars.join("*"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolicError {
+    Fault { pc: usize, kind: FaultKind },
+    /// A value the VM has to know exactly, such as an instruction, an
+    /// address or a jump condition, depends on a variable.
+    NotConstant { pc: usize, what: &'static str, expr: Expr },
+    /// A value the VM needs was read through an address that depends on a
+    /// variable, so nothing is known about it.
+    Unknown { pc: usize, what: &'static str },
+    /// Writing through an address that depends on a variable could change
+    /// any cell.
+    SymbolicWrite { pc: usize, expr: Expr },
+    /// Input and output have no symbolic meaning.
+    Unsupported { pc: usize, opcode: Opcode },
+    /// A coefficient of a result doesn't fit in an `isize`.
+    Overflow { pc: usize },
+    /// The program halted without `addr` being known.
+    UnknownResult { addr: usize },
+    /// No choice of the variables gives `target`.
+    NoSolution { target: isize },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolicError::Fault { pc, kind } => write!(f, "{} at pc {}", kind, pc),
+            SymbolicError::NotConstant { pc, what, expr } =>
+                write!(f, "the {} at pc {} depends on variables: {}", what, pc, expr),
+            SymbolicError::Unknown { pc, what } =>
+                write!(f, "the {} at pc {} was read through a variable address", what, pc),
+            SymbolicError::SymbolicWrite { pc, expr } =>
+                write!(f, "the write at pc {} goes to a variable address: {}", pc, expr),
+            SymbolicError::Unsupported { pc, opcode } =>
+                write!(f, "{:?} at pc {} can't be evaluated symbolically", opcode, pc),
+            SymbolicError::Overflow { pc } => write!(f, "overflow at pc {}", pc),
+            SymbolicError::UnknownResult { addr } =>
+                write!(f, "memory[{}] was read through a variable address", addr),
+            SymbolicError::NoSolution { target } => write!(f, "no values of the variables give {}", target),
+        }
+    }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/// Runs a program with some memory cells replaced by variables, tracking
+/// what every cell works out to in terms of them. Only arithmetic can be
+/// symbolic; anything that steers execution must stay constant.
+///
+/// Reading through an address that depends on a variable gives an unknown
+/// value (`None`). That's only an error if the value is later needed, so a
+/// result that gets overwritten unused, as in day 2, does no harm.
+///
+/// Like `Memory`, only cells that have been loaded or written are stored,
+/// so a write to a huge address costs one entry.
+pub struct Symbolic {
+    memory: BTreeMap<usize, Option<Expr>>,
+    relative_base: isize,
+    pc: usize,
+}
+
+impl Symbolic {
+
+    pub fn new(program: &[isize], variables: &[(usize, &str)]) -> Symbolic {
+        let mut memory : BTreeMap<usize, Option<Expr>> = program.iter()
+            .enumerate()
+            .map(|(addr, &v)| (addr, Some(Expr::constant(v))))
+            .collect();
+        for &(addr, name) in variables {
+            memory.insert(addr, Some(Expr::var(name)));
+        }
+        Symbolic { memory, relative_base: 0, pc: 0 }
+    }
+
+    /// What the cell at `addr` holds, or `None` if it can't be known.
+    pub fn get(&self, addr: usize) -> Option<Expr> {
+        self.memory.get(&addr).cloned().unwrap_or_else(|| Some(Expr::default()))
+    }
+
+    fn constant(&self, value: Option<Expr>, what: &'static str) -> Result<isize, SymbolicError> {
+        let expr = value.ok_or(SymbolicError::Unknown { pc: self.pc, what })?;
+        expr.as_constant().ok_or(SymbolicError::NotConstant { pc: self.pc, what, expr })
+    }
+
+    fn fault(&self, kind: FaultKind) -> SymbolicError {
+        SymbolicError::Fault { pc: self.pc, kind }
+    }
+
+    fn address(&self, mode: Mode, param: isize) -> Result<usize, SymbolicError> {
+        let base = if mode == Mode::Relative { self.relative_base } else { 0 };
+        offset_address(base, param).map_err(|kind| self.fault(kind))
+    }
+
+    fn read(&self, mode: Mode, offset: usize) -> Result<Option<Expr>, SymbolicError> {
+        let param = self.get(self.pc + offset);
+        if mode == Mode::Immediate {
+            return Ok(param);
+        }
+        match param.as_ref().and_then(Expr::as_constant) {
+            Some(param) => Ok(self.get(self.address(mode, param)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn write(&mut self, mode: Mode, offset: usize, value: Option<Expr>) -> Result<(), SymbolicError> {
+        let param = match self.get(self.pc + offset) {
+            Some(param) => param.as_constant()
+                .ok_or(SymbolicError::SymbolicWrite { pc: self.pc, expr: param })?,
+            None => return Err(SymbolicError::Unknown { pc: self.pc, what: "write address" }),
+        };
+        let addr = self.address(mode, param)?;
+        self.memory.insert(addr, value);
+        Ok(())
+    }
+
+    fn read_constant(&self, mode: Mode, offset: usize, what: &'static str) -> Result<isize, SymbolicError> {
+        self.constant(self.read(mode, offset)?, what)
+    }
+
+    /// Runs until the program halts.
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        loop {
+            let word = self.constant(self.get(self.pc), "instruction")?;
+            let instruction = Instruction::decode(word).map_err(|kind| self.fault(kind))?;
+            let [mode1, mode2, mode3] = instruction.modes;
+
+            match instruction.opcode {
+                Opcode::Halt => return Ok(()),
+                Opcode::Add | Opcode::Multiply => {
+                    let value = match (self.read(mode1, 1)?, self.read(mode2, 2)?) {
+                        (Some(in1), Some(in2)) => {
+                            let value = if instruction.opcode == Opcode::Add {
+                                in1.checked_add(&in2)
+                            } else {
+                                in1.checked_mul(&in2)
+                            };
+                            Some(value.ok_or(SymbolicError::Overflow { pc: self.pc })?)
+                        },
+                        _ => None,
+                    };
+                    self.write(mode3, 3, value)?;
+                },
+                Opcode::LessThan | Opcode::Equals => {
+                    let in1 = self.read_constant(mode1, 1, "comparison")?;
+                    let in2 = self.read_constant(mode2, 2, "comparison")?;
+                    let result = if instruction.opcode == Opcode::LessThan { in1 < in2 } else { in1 == in2 };
+                    self.write(mode3, 3, Some(Expr::constant(result as isize)))?;
+                },
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                    let condition = self.read_constant(mode1, 1, "jump condition")?;
+                    let target = self.read_constant(mode2, 2, "jump target")?;
+                    if (condition != 0) == (instruction.opcode == Opcode::JumpIfTrue) {
+                        self.pc = self.address(Mode::Position, target)?;
+                        continue;
+                    }
+                },
+                Opcode::AdjustRelativeBase => {
+                    let adjustment = self.read_constant(mode1, 1, "relative base adjustment")?;
+                    self.relative_base = self.relative_base.checked_add(adjustment)
+                        .ok_or_else(|| self.fault(FaultKind::Overflow))?;
+                },
+                opcode @ Opcode::Input | opcode @ Opcode::Output => {
+                    return Err(SymbolicError::Unsupported { pc: self.pc, opcode });
+                },
+            }
+            self.pc += instruction.width();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expr_example() {
+        let (a, b) = (Expr::var("a"), Expr::var("b"));
+        let ab_plus_a = a.checked_mul(&b).unwrap().checked_add(&a).unwrap();
+        let expr = ab_plus_a.checked_mul(&b.checked_add(&Expr::constant(-2)).unwrap()).unwrap();
+        assert_eq!("a*b*b - a*b - 2*a", expr.to_string());
+        assert_eq!(3, expr.degree());
+        assert_eq!(None, expr.linear_in("a"));
+        assert_eq!(Some((4, 0)), expr.substitute("b", 3).unwrap().linear_in("a"));
+        assert_eq!(Some(-10), expr.substitute("a", 5).and_then(|e| e.substitute("b", 0)).unwrap().as_constant());
+        assert_eq!(None, expr.substitute("b", isize::MAX));
+        assert_eq!(None, Expr::constant(isize::MAX).checked_add(&Expr::constant(1)));
+    }
+
+    #[test]
+    fn run_example() {
+        // [3] = [noun] + [verb] is overwritten unused, then [0] = ([1] + [2]) * [13]
+        let mut vm = Symbolic::new(&[1,0,0,3,1,1,2,3,2,3,13,0,99,3], &[(1, "noun"), (2, "verb")]);
+        vm.run().unwrap();
+        assert_eq!(Some("3*noun + 3*verb".to_string()), vm.get(0).map(|e| e.to_string()));
+
+        let mut vm = Symbolic::new(&[1106,0,99,99], &[(1, "x")]);
+        assert_eq!(
+            Err(SymbolicError::NotConstant { pc: 0, what: "jump condition", expr: Expr::var("x") }),
+            vm.run());
+
+        let mut vm = Symbolic::new(&[1,0,0,3,1005,3,0,99], &[(1, "x")]);
+        assert_eq!(Err(SymbolicError::Unknown { pc: 4, what: "jump condition" }), vm.run());
+
+        // Variables can live past the end of the program.
+        let mut vm = Symbolic::new(&[1,10,10,0,99], &[(10, "x")]);
+        vm.run().unwrap();
+        assert_eq!(Some("2*x".to_string()), vm.get(0).map(|e| e.to_string()));
+
+        // Far writes and variables are stored sparsely, as in `Memory`.
+        let far = 1_000_000_000_000;
+        let mut vm = Symbolic::new(&[1101,2,3,far,1,far,far + 1,0,99], &[(far as usize + 1, "x")]);
+        vm.run().unwrap();
+        assert_eq!(Some(Expr::constant(5)), vm.get(far as usize));
+        assert_eq!(Some("x + 5".to_string()), vm.get(0).map(|e| e.to_string()));
+
+        let mut vm = Symbolic::new(&[1102,-1,isize::MAX,0,1002,0,3,0,99], &[]);
+        assert_eq!(Err(SymbolicError::Overflow { pc: 4 }), vm.run());
+
+        let mut vm = Symbolic::new(&[109,isize::MAX,109,1,99], &[]);
+        assert_eq!(Err(SymbolicError::Fault { pc: 2, kind: FaultKind::Overflow }), vm.run());
+    }
+}
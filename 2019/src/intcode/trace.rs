@@ -0,0 +1,153 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use fnv::FnvHashMap;
+
+use super::Opcode;
+use super::disasm::Line;
+
+/// Execution counts gathered by a `Tracer`.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub total: u64,
+    pub opcodes: FnvHashMap<Opcode, u64>,
+    pub pcs: FnvHashMap<usize, u64>,
+}
+
+impl Profile {
+
+    /// The `n` most executed addresses, busiest first.
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut pcs : Vec<(usize, u64)> = self.pcs.iter().map(|(&pc, &count)| (pc, count)).collect();
+        pcs.sort_by_key(|&(pc, count)| (std::cmp::Reverse(count), pc));
+        pcs.truncate(n);
+        pcs
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} instructions", self.total)?;
+
+        let mut opcodes : Vec<(Opcode, u64)> = self.opcodes.iter().map(|(&op, &count)| (op, count)).collect();
+        opcodes.sort_by_key(|&(op, count)| (std::cmp::Reverse(count), op.code()));
+        for (opcode, count) in opcodes {
+            let percent = 100.0 * count as f64 / self.total as f64;
+            writeln!(f, "  {:<4}{:>12} {:>6.2}%", opcode.mnemonic(), count, percent)?;
+        }
+
+        writeln!(f, "hottest pcs:")?;
+        for (pc, count) in self.hottest(10) {
+            writeln!(f, "  {:04}{:>12}", pc, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the instructions a machine executes. Attach one with
+/// `IntCode::set_tracer`; machines without a tracer pay nothing for it.
+///
+/// The trace is one line per instruction, the disassembly followed by the
+/// relative base, e.g. `0012: ADD [rb+3], #5 -> [100] ; rb 2000`.
+#[derive(Default)]
+pub struct Tracer {
+    sink: Option<Box<dyn Write + Send>>,
+    error: Option<io::Error>,
+    profile: Profile,
+}
+
+impl Tracer {
+
+    /// A tracer that only gathers a profile.
+    pub fn new() -> Tracer {
+        Tracer::default()
+    }
+
+    pub fn to_writer<W: Write + Send + 'static>(sink: W) -> Tracer {
+        Tracer { sink: Some(Box::new(sink)), ..Tracer::default() }
+    }
+
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Tracer> {
+        Ok(Tracer::to_writer(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Wraps the tracer so it can be shared by a machine and its forks.
+    pub fn shared(self) -> Arc<Mutex<Tracer>> {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Flushes the trace, reporting the first write error if there was one.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        match &mut self.sink {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub(super) fn record(&mut self, line: &Line, relative_base: isize) {
+        if let Line::Instruction { addr, instruction, .. } = line {
+            self.profile.total += 1;
+            *self.profile.opcodes.entry(instruction.opcode).or_insert(0) += 1;
+            *self.profile.pcs.entry(*addr).or_insert(0) += 1;
+        }
+
+        if let (Some(sink), None) = (&mut self.sink, &self.error) {
+            if let Err(error) = writeln!(sink, "{} ; rb {}", line, relative_base) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntCode;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn profile_example() {
+        // Counts down from 3, outputting each value.
+        let mut comp = IntCode::with_inputs(&[3,20,4,20,1001,20,-1,20,1005,20,2,99], &[3]);
+        let buffer = SharedBuffer::default();
+        let tracer = Tracer::to_writer(buffer.clone()).shared();
+        comp.set_tracer(tracer.clone());
+
+        assert_eq!(Ok(vec![3, 2, 1]), comp.run_to_halt());
+
+        let tracer = tracer.lock().unwrap();
+        let profile = tracer.profile();
+        assert_eq!(11, profile.total);
+        assert_eq!(Some(&3), profile.opcodes.get(&Opcode::Add));
+        assert_eq!(Some(&1), profile.opcodes.get(&Opcode::Halt));
+        assert_eq!(vec![(2, 3), (4, 3)], profile.hottest(2));
+
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines : Vec<&str> = trace.lines().collect();
+        assert_eq!(11, lines.len());
+        assert_eq!("0000: IN -> [20] ; rb 0", lines[0]);
+        assert_eq!("0011: HLT ; rb 0", lines[10]);
+    }
+}
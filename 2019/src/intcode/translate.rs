@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use super::{FaultKind, Instruction};
+use super::memory::Memory;
+use super::word::Word;
+
+/// An instruction decoded together with its raw parameters, so executing it
+/// doesn't have to touch the mode digits or fetch from memory again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Op<W: Word = isize> {
+    pub instruction: Instruction,
+    pub params: [W; 3],
+}
+
+impl<W: Word> Op<W> {
+
+    pub fn decode(memory: &Memory<W>, addr: usize) -> Result<Op<W>, FaultKind> {
+        let word = memory.get(addr).to_isize().ok_or(FaultKind::UnknownOpcode)?;
+        let instruction = Instruction::decode(word)?;
+        let mut params : [W; 3] = Default::default();
+        for (i, param) in params.iter_mut().enumerate().take(instruction.width() - 1) {
+            *param = memory.get(addr + 1 + i);
+        }
+        Ok(Op { instruction, params })
+    }
+}
+
+const PAGE_SIZE : usize = 256;
+
+type Page<W> = Arc<Vec<Option<Op<W>>>>;
+
+/// A pre-decoded copy of a program, one slot per address of the original
+/// image. Every address that decodes is translated up front, since we can't
+/// tell code from data without running it; anything else is decoded on
+/// demand. Writes that overlap a translated instruction throw it away, so
+/// self-modifying programs behave exactly as they do when interpreted.
+/// Clones share pages of slots the way `Memory` does, copying each one the
+/// first time either side changes it.
+#[derive(Clone, Debug, Default)]
+pub struct Translation<W: Word = isize> {
+    pages: Vec<Page<W>>,
+    len: usize,
+    invalidations: usize,
+}
+
+impl<W: Word> Translation<W> {
+
+    pub fn new(memory: &Memory<W>, len: usize) -> Translation<W> {
+        let pages = (0..len).step_by(PAGE_SIZE)
+            .map(|start| {
+                let end = std::cmp::min(start + PAGE_SIZE, len);
+                Arc::new((start..end).map(|addr| Op::decode(memory, addr).ok()).collect())
+            })
+            .collect();
+        Translation { pages, len, invalidations: 0 }
+    }
+
+    pub fn get(&mut self, memory: &Memory<W>, pc: usize) -> Result<Op<W>, FaultKind> {
+        if pc >= self.len {
+            return Op::decode(memory, pc);
+        }
+        let page = &mut self.pages[pc / PAGE_SIZE];
+        if let Some(op) = &page[pc % PAGE_SIZE] {
+            return Ok(op.clone());
+        }
+        let op = Op::decode(memory, pc)?;
+        Arc::make_mut(page)[pc % PAGE_SIZE] = Some(op.clone());
+        Ok(op)
+    }
+
+    /// Forgets every instruction that covers `addr`.
+    pub fn invalidate(&mut self, addr: usize) {
+        let end = std::cmp::min(addr + 1, self.len);
+        for pc in addr.saturating_sub(3)..end {
+            let page = &mut self.pages[pc / PAGE_SIZE];
+            // Only copy a shared page when there's something to forget.
+            if page[pc % PAGE_SIZE].is_some() {
+                Arc::make_mut(page)[pc % PAGE_SIZE] = None;
+                self.invalidations += 1;
+            }
+        }
+    }
+
+    /// How many addresses of the original image are covered.
+    pub fn image_len(&self) -> usize {
+        self.len
+    }
+
+    /// How many pages of slots are still shared with a clone.
+    pub fn shared_pages(&self) -> usize {
+        self.pages.iter().filter(|page| Arc::strong_count(page) > 1).count()
+    }
+
+    /// How many translated instructions have been thrown away by writes.
+    pub fn invalidations(&self) -> usize {
+        self.invalidations
+    }
+}
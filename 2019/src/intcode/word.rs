@@ -0,0 +1,87 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+/// A type the VM can keep in memory. Puzzles only need `isize`, but wider
+/// (or unbounded) words let a program run past where `isize` would overflow.
+pub trait Word: Clone + Default + Ord + fmt::Debug + fmt::Display + FromStr + Send + Sync + 'static {
+    fn from_isize(n: isize) -> Self;
+
+    /// `None` if the value doesn't fit.
+    fn to_isize(&self) -> Option<isize>;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The nearest `isize`, for reporting values that may not fit.
+    fn saturating_to_isize(&self) -> isize {
+        self.to_isize().unwrap_or(if *self < Self::default() { isize::MIN } else { isize::MAX })
+    }
+}
+
+macro_rules! primitive_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            fn from_isize(n: isize) -> $t {
+                n as $t
+            }
+
+            fn to_isize(&self) -> Option<isize> {
+                isize::try_from(*self).ok()
+            }
+
+            fn checked_add(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn wrapping_add(&self, other: &$t) -> $t {
+                <$t>::wrapping_add(*self, *other)
+            }
+
+            fn wrapping_mul(&self, other: &$t) -> $t {
+                <$t>::wrapping_mul(*self, *other)
+            }
+        }
+    )*};
+}
+
+primitive_word!(isize, i64, i128);
+
+/// Never overflows, so checked and wrapping arithmetic are the same.
+impl Word for BigInt {
+    fn from_isize(n: isize) -> BigInt {
+        BigInt::from(n)
+    }
+
+    fn to_isize(&self) -> Option<isize> {
+        isize::try_from(self).ok()
+    }
+
+    fn checked_add(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self * other)
+    }
+
+    fn wrapping_add(&self, other: &BigInt) -> BigInt {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &BigInt) -> BigInt {
+        self * other
+    }
+}
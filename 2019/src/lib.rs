@@ -1,23 +1,23 @@
 use aoc_runner_derive::aoc_lib;
 
-mod intcode;
+pub mod intcode;
 // mod day1;
-// mod day2;
+mod day2;
 // mod day3;
 // mod day4;
-// mod day5;
+mod day5;
 // mod day6;
-// mod day7;
-// mod day8;
-// mod day9;
+mod day7;
+mod day8;
+mod day9;
 // mod day10;
-// mod day11;
+mod day11;
 // mod day12;
-// mod day13;
+mod day13;
 // mod day14;
-// mod day15;
-// mod day17;
-// mod day23;
+mod day15;
+mod day17;
+mod day23;
 mod day24;
 
 aoc_lib! { year = 2019, extra_alternatives = ["fnv"] 
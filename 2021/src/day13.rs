@@ -1,6 +1,8 @@
 use std::{fmt::Display, collections::BTreeSet, str::Lines};
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use aoc_common::canvas::Canvas;
+use aoc_common::ocr;
 
 #[derive(Clone)]
 struct SparseGrid {
@@ -20,6 +22,14 @@ impl SparseGrid {
         self.dots.iter().map(|(x,_)| *x).max().unwrap() + 1
     }
 
+    fn to_canvas(&self) -> Canvas<bool> {
+        let mut canvas = Canvas::new();
+        for &(x, y) in &self.dots {
+            canvas.set(x as isize, y as isize, true);
+        }
+        canvas
+    }
+
     fn parse(mut lines: &mut Lines) -> SparseGrid {
         let mut dots = BTreeSet::new();
         for line in &mut lines {
@@ -129,7 +139,7 @@ fn part1(ins: &(SparseGrid,Vec<Fold>)) -> usize {
 }
 
 #[aoc(day13, part2)]
-fn part2(ins: &(SparseGrid,Vec<Fold>)) -> SparseGrid { 
+fn part2(ins: &(SparseGrid,Vec<Fold>)) -> String { 
     let (grid, folds) = ins;
     let mut grid: SparseGrid = grid.clone();
 
@@ -137,7 +147,7 @@ fn part2(ins: &(SparseGrid,Vec<Fold>)) -> SparseGrid {
         fold(&mut grid, *f);
     }
 
-    grid
+    ocr::read_canvas(&grid.to_canvas())
 }
 
 #[cfg(test)]
@@ -218,5 +228,7 @@ fold along x=5"#);
 #...#
 #####
 "#);
+
+        assert_eq!(input.0.to_canvas().to_pbm(), "P1\n5 5\n1 1 1 1 1\n1 0 0 0 1\n1 0 0 0 1\n1 0 0 0 1\n1 1 1 1 1\n");
     }
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// The smallest rectangle covering every pixel that has been set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: isize,
+    pub min_y: isize,
+    pub max_x: isize,
+    pub max_y: isize,
+}
+
+impl Bounds {
+    pub fn width(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max_y - self.min_y + 1) as usize
+    }
+
+    fn include(&mut self, x: isize, y: isize) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+}
+
+/// A picture drawn one pixel at a time at any coordinates, positive or
+/// negative. Pixels that were never set read as `T::default()`.
+///
+/// Exported images cover the bounds of the set pixels, so a picture drawn
+/// around (-3, 5) comes out the same as one drawn around the origin.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Canvas<T> {
+    pixels: BTreeMap<(isize, isize), T>,
+    bounds: Option<Bounds>,
+}
+
+impl<T: Copy + Default> Canvas<T> {
+
+    pub fn new() -> Canvas<T> {
+        Canvas { pixels: BTreeMap::new(), bounds: None }
+    }
+
+    pub fn set(&mut self, x: isize, y: isize, pixel: T) {
+        self.pixels.insert((x, y), pixel);
+        match &mut self.bounds {
+            Some(bounds) => bounds.include(x, y),
+            None => self.bounds = Some(Bounds { min_x: x, min_y: y, max_x: x, max_y: y }),
+        }
+    }
+
+    pub fn get(&self, x: isize, y: isize) -> T {
+        self.pixels.get(&(x, y)).cloned().unwrap_or_default()
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    /// How many pixels have been set, including any set back to the default.
+    pub fn len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = ((isize, isize), T)> + '_ {
+        self.pixels.iter().map(|(&xy, &pixel)| (xy, pixel))
+    }
+
+    /// Every row inside the bounds, top to bottom.
+    pub fn rows(&self) -> Vec<Vec<T>> {
+        match self.bounds {
+            Some(b) => (b.min_y..=b.max_y)
+                .map(|y| (b.min_x..=b.max_x).map(|x| self.get(x, y)).collect())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The picture as text, one line per row.
+    pub fn render<F: Fn(T) -> char>(&self, glyph: F) -> String {
+        let mut text = String::new();
+        for row in self.rows() {
+            text.extend(row.into_iter().map(&glyph));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Writes a plain (P1) bitmap, with `ink` picking the black pixels.
+    pub fn write_pbm<W: Write, F: Fn(T) -> bool>(&self, out: &mut W, ink: F) -> io::Result<()> {
+        let (width, height) = self.size();
+        writeln!(out, "P1\n{} {}", width, height)?;
+        for row in self.rows() {
+            let row : Vec<&str> = row.into_iter().map(|p| if ink(p) { "1" } else { "0" }).collect();
+            writeln!(out, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes a plain (P3) pixmap, with `color` giving each pixel's RGB.
+    pub fn write_ppm<W: Write, F: Fn(T) -> [u8; 3]>(&self, out: &mut W, color: F) -> io::Result<()> {
+        let (width, height) = self.size();
+        writeln!(out, "P3\n{} {}\n255", width, height)?;
+        for row in self.rows() {
+            let row : Vec<String> = row.into_iter()
+                .map(|p| {
+                    let [r, g, b] = color(p);
+                    format!("{} {} {}", r, g, b)
+                })
+                .collect();
+            writeln!(out, "{}", row.join("  "))?;
+        }
+        Ok(())
+    }
+
+    pub fn save_pbm<P: AsRef<Path>, F: Fn(T) -> bool>(&self, path: P, ink: F) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_pbm(&mut out, ink)?;
+        out.flush()
+    }
+
+    pub fn save_ppm<P: AsRef<Path>, F: Fn(T) -> [u8; 3]>(&self, path: P, color: F) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_ppm(&mut out, color)?;
+        out.flush()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.bounds.map_or((0, 0), |b| (b.width(), b.height()))
+    }
+}
+
+impl Canvas<bool> {
+
+    pub fn to_pbm(&self) -> String {
+        let mut out = Vec::new();
+        self.write_pbm(&mut out, |p| p).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+}
+
+/// Shows lit pixels as `#`, starting on a new line so the runner's answer
+/// label doesn't skew the first row.
+impl fmt::Display for Canvas<bool> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\n{}", self.render(|p| if p { '#' } else { '.' }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_example() {
+        let mut canvas = Canvas::new();
+        assert_eq!(None, canvas.bounds());
+        canvas.set(-1, 2, true);
+        canvas.set(1, 3, true);
+        assert_eq!(Some(Bounds { min_x: -1, min_y: 2, max_x: 1, max_y: 3 }), canvas.bounds());
+        assert_eq!("#..\n..#\n", canvas.render(|p| if p { '#' } else { '.' }));
+        assert!(!canvas.get(0, 0));
+    }
+
+    #[test]
+    fn export_example() {
+        let mut canvas = Canvas::new();
+        canvas.set(0, 0, 1u8);
+        canvas.set(1, 1, 2u8);
+
+        let mut pbm = Vec::new();
+        canvas.write_pbm(&mut pbm, |p| p == 1).unwrap();
+        assert_eq!("P1\n2 2\n1 0\n0 0\n", String::from_utf8(pbm).unwrap());
+
+        let mut ppm = Vec::new();
+        canvas.write_ppm(&mut ppm, |p| [p * 100, 0, 255]).unwrap();
+        assert_eq!("P3\n2 2\n255\n100 0 255  0 0 255\n0 0 255  200 0 255\n", String::from_utf8(ppm).unwrap());
+    }
+}
@@ -0,0 +1,4 @@
+//! Helpers shared by more than one year's solutions.
+
+pub mod canvas;
+pub mod ocr;
@@ -0,0 +1,199 @@
+//! Reads the capital letters Advent of Code draws with lit pixels, in both
+//! the usual 4x6 font and the larger 6x10 one.
+
+use std::collections::HashMap;
+
+use crate::canvas::Canvas;
+
+const SMALL : &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+const LARGE : &[(char, &str)] = &[
+    ('A', "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#"),
+    ('B', "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####."),
+    ('C', ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####."),
+    ('E', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('F', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('G', ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#"),
+    ('H', "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#"),
+    ('J', "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###.."),
+    ('K', "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#"),
+    ('L', "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('N', "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#"),
+    ('P', "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('R', "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#"),
+    ('X', "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#"),
+    ('Z', "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######"),
+];
+
+/// Width of each letter's cell in the small font, including the gap.
+/// `Y` fills its whole cell, so letters can't always be split on blank columns.
+const SMALL_CELL : usize = 5;
+
+/// Renders columns `range` of `rows` as a glyph, trimmed of blank columns.
+fn glyph(rows: &[Vec<bool>], range: std::ops::Range<usize>) -> String {
+    let lit = |x: usize| rows.iter().any(|row| row.get(x) == Some(&true));
+    let start = range.clone().find(|&x| lit(x)).unwrap_or(range.start);
+    let end = range.clone().rev().find(|&x| lit(x)).map_or(start, |x| x + 1);
+    let glyph : Vec<String> = rows.iter()
+        .map(|row| (start..end).map(|x| if row.get(x) == Some(&true) { '#' } else { '.' }).collect())
+        .collect();
+    glyph.join("\n")
+}
+
+/// Splits on blank columns, for fonts where every letter has a gap after it.
+fn read_separated(rows: &[Vec<bool>], font: &HashMap<String, char>) -> String {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |x: usize| rows.iter().any(|row| row.get(x) == Some(&true));
+    let mut text = String::new();
+    let mut x = 0;
+    while x < width {
+        if !lit(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < width && lit(x) {
+            x += 1;
+        }
+        text.push(*font.get(&glyph(rows, start..x)).unwrap_or(&'?'));
+    }
+    text
+}
+
+/// Reads fixed-width cells starting at the first lit column.
+fn read_cells(rows: &[Vec<bool>], font: &HashMap<String, char>, cell: usize) -> String {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let first = (0..width).find(|&x| rows.iter().any(|row| row.get(x) == Some(&true))).unwrap_or(width);
+    (first..width).step_by(cell)
+        .map(|start| glyph(rows, start..std::cmp::min(start + cell, width)))
+        .filter(|glyph| glyph.contains('#'))
+        .map(|glyph| *font.get(&glyph).unwrap_or(&'?'))
+        .collect()
+}
+
+fn font(glyphs: &[(char, &str)]) -> HashMap<String, char> {
+    glyphs.iter()
+        .map(|&(c, g)| {
+            let rows : Vec<Vec<bool>> = g.lines().map(|line| line.chars().map(|p| p == '#').collect()).collect();
+            (glyph(&rows, 0..rows[0].len()), c)
+        })
+        .collect()
+}
+
+/// Reads the letters drawn in `rows`, which may have any amount of blank
+/// space around them. Anything that isn't a known letter comes back as `?`.
+pub fn read(rows: &[Vec<bool>]) -> String {
+    let lit_rows : Vec<usize> = (0..rows.len()).filter(|&y| rows[y].iter().any(|&p| p)).collect();
+    let (top, bottom) = match (lit_rows.first(), lit_rows.last()) {
+        (Some(&top), Some(&bottom)) => (top, bottom),
+        _ => return String::new(),
+    };
+    let rows = &rows[top..=bottom];
+
+    match rows.len() {
+        6 => {
+            let font = font(SMALL);
+            let cells = read_cells(rows, &font, SMALL_CELL);
+            if !cells.contains('?') {
+                return cells;
+            }
+            // Not laid out on the usual grid, so fall back to the gaps.
+            let separated = read_separated(rows, &font);
+            if separated.matches('?').count() < cells.matches('?').count() { separated } else { cells }
+        },
+        10 => read_separated(rows, &font(LARGE)),
+        _ => read_separated(rows, &HashMap::new()),
+    }
+}
+
+pub fn read_canvas(canvas: &Canvas<bool>) -> String {
+    read(&canvas.rows())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(picture: &str) -> Vec<Vec<bool>> {
+        picture.lines().map(|line| line.chars().map(|c| c == '#').collect()).collect()
+    }
+
+    #[test]
+    fn small_example() {
+        assert_eq!("AURCY", read(&grid("\
+.##..#..#.###...##..#...#
+#..#.#..#.#..#.#..#.#...#
+#..#.#..#.#..#.#.....#.#.
+####.#..#.###..#......#..
+#..#.#..#.#.#..#..#...#..
+#..#..##..#..#..##....#..")));
+
+        assert_eq!("HAFULAPE", read(&grid("\
+...........................................
+.#..#..##..####.#..#.#.....##..###..####...
+.#..#.#..#.#....#..#.#....#..#.#..#.#......
+.####.#..#.###..#..#.#....#..#.#..#.###....
+.#..#.####.#....#..#.#....####.###..#......
+.#..#.#..#.#....#..#.#....#..#.#....#......
+.#..#.#..#.#.....##..####.#..#.#....####...")));
+    }
+
+    #[test]
+    fn wide_letter_example() {
+        // Y touches the next letter's cell, with no blank column between them.
+        let letters = ['B', 'Y', 'E'];
+        let picture : Vec<String> = (0..6).map(|y| {
+            letters.iter()
+                .map(|&c| {
+                    let glyph = SMALL.iter().find(|(l, _)| *l == c).unwrap().1;
+                    format!("{:.<5}", glyph.lines().nth(y).unwrap())
+                })
+                .collect()
+        }).collect();
+        assert_eq!("BYE", read(&grid(&picture.join("\n"))));
+        assert_eq!("YA", read(&grid("\
+#...#.##..
+#...##..#.
+.#.#.#..#.
+..#..####.
+..#..#..#.
+..#..#..#.")));
+    }
+
+    #[test]
+    fn large_example() {
+        let glyphs : Vec<Vec<&str>> = ["H", "N", "X"].iter()
+            .map(|&c| LARGE.iter().find(|(l, _)| l.to_string() == c).unwrap().1.lines().collect())
+            .collect();
+        let picture : Vec<String> = (0..10).map(|y| {
+            let row : Vec<&str> = glyphs.iter().map(|g| g[y]).collect();
+            row.join("..")
+        }).collect();
+        assert_eq!("HNX", read(&grid(&picture.join("\n"))));
+    }
+
+    #[test]
+    fn unknown_example() {
+        assert_eq!("?", read(&grid("####\n####\n####\n####\n####\n####")));
+        assert_eq!("", read(&grid("....\n....")));
+    }
+}